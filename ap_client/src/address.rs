@@ -0,0 +1,210 @@
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::str::FromStr;
+
+pub const DEFAULT_PORT: u16 = 38281;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Ws,
+    Wss,
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scheme::Ws => f.write_str("ws"),
+            Scheme::Wss => f.write_str("wss"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Host {
+    Name(String),
+    Ipv6(Ipv6Addr),
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Name(name) => f.write_str(name),
+            Host::Ipv6(ip) => write!(f, "[{}]", ip),
+        }
+    }
+}
+
+/// A server address as typed by the user, e.g. `archipelago.gg:38281`,
+/// `wss://host:port` or `[::1]:38281`.
+///
+/// When no scheme is given the caller is expected to try `wss` first and fall
+/// back to `ws`, like the official clients do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerAddress {
+    pub scheme: Option<Scheme>,
+    pub host: Host,
+    pub port: u16,
+}
+
+impl ServerAddress {
+    pub fn url(&self, scheme: Scheme) -> String {
+        format!("{}://{}:{}", scheme, self.host, self.port)
+    }
+}
+
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.scheme {
+            Some(scheme) => f.write_str(&self.url(scheme)),
+            None => write!(f, "{}:{}", self.host, self.port),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    Empty,
+    UnsupportedScheme(String),
+    InvalidHost(String),
+    InvalidPort(String),
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::Empty => f.write_str("server address is empty"),
+            AddressError::UnsupportedScheme(scheme) if scheme == "http" || scheme == "https" => {
+                f.write_str("room page links do not contain the port, use the address shown on the room page")
+            }
+            AddressError::UnsupportedScheme(scheme) => {
+                write!(f, "unsupported scheme \"{}\", use ws:// or wss://", scheme)
+            }
+            AddressError::InvalidHost(host) => write!(f, "invalid host \"{}\"", host),
+            AddressError::InvalidPort(port) => write!(f, "invalid port \"{}\"", port),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+impl FromStr for ServerAddress {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(AddressError::Empty);
+        }
+
+        let (scheme, rest) = match s.split_once("://") {
+            Some((scheme, rest)) => match scheme.to_ascii_lowercase().as_str() {
+                "ws" => (Some(Scheme::Ws), rest),
+                "wss" => (Some(Scheme::Wss), rest),
+                other => return Err(AddressError::UnsupportedScheme(other.to_owned())),
+            },
+            None => (None, s),
+        };
+
+        // Anything after the authority (a trailing slash or path) is ignored.
+        let authority = rest.split('/').next().unwrap_or_default();
+        if authority.is_empty() {
+            return Err(AddressError::InvalidHost(authority.to_owned()));
+        }
+
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let (ip, after) = bracketed
+                .split_once(']')
+                .ok_or_else(|| AddressError::InvalidHost(authority.to_owned()))?;
+            let ip = Ipv6Addr::from_str(ip).map_err(|_| AddressError::InvalidHost(ip.to_owned()))?;
+            let port = match after {
+                "" => None,
+                after => Some(
+                    after
+                        .strip_prefix(':')
+                        .ok_or_else(|| AddressError::InvalidPort(after.to_owned()))?,
+                ),
+            };
+            (Host::Ipv6(ip), port)
+        } else if authority.matches(':').count() > 1 {
+            // Bare IPv6 literal, which cannot carry a port without brackets.
+            let ip = Ipv6Addr::from_str(authority)
+                .map_err(|_| AddressError::InvalidHost(authority.to_owned()))?;
+            (Host::Ipv6(ip), None)
+        } else {
+            let (name, port) = match authority.split_once(':') {
+                Some((name, port)) => (name, Some(port)),
+                None => (authority, None),
+            };
+            if !is_valid_host_name(name) {
+                return Err(AddressError::InvalidHost(name.to_owned()));
+            }
+            (Host::Name(name.to_ascii_lowercase()), port)
+        };
+
+        let port = match port {
+            None => DEFAULT_PORT,
+            Some(port) => match port.parse::<u16>() {
+                Ok(port) if port != 0 => port,
+                _ => return Err(AddressError::InvalidPort(port.to_owned())),
+            },
+        };
+
+        Ok(Self { scheme, host, port })
+    }
+}
+
+fn is_valid_host_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(address: &str) -> Result<ServerAddress, AddressError> {
+        address.parse()
+    }
+
+    #[test]
+    fn parses_host_and_port() {
+        let address = parse("archipelago.gg:38281").unwrap();
+        assert_eq!(address.scheme, None);
+        assert_eq!(address.host, Host::Name(String::from("archipelago.gg")));
+        assert_eq!(address.port, 38281);
+
+        let address = parse("wss://Example.com:40000/").unwrap();
+        assert_eq!(address.scheme, Some(Scheme::Wss));
+        assert_eq!(address.url(Scheme::Wss), "wss://example.com:40000");
+        assert_eq!(parse("localhost").unwrap().port, DEFAULT_PORT);
+    }
+
+    #[test]
+    fn parses_ipv6_literals() {
+        let address = parse("[::1]:38281").unwrap();
+        assert_eq!(address.host, Host::Ipv6(Ipv6Addr::LOCALHOST));
+        assert_eq!(address.to_string(), "[::1]:38281");
+
+        let address = parse("::1").unwrap();
+        assert_eq!(address.host, Host::Ipv6(Ipv6Addr::LOCALHOST));
+        assert_eq!(address.port, DEFAULT_PORT);
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        assert_eq!(parse("host:0"), Err(AddressError::InvalidPort(String::from("0"))));
+        assert_eq!(parse("host:"), Err(AddressError::InvalidPort(String::new())));
+        assert_eq!(parse("  "), Err(AddressError::Empty));
+
+        let err = parse("https://archipelago.gg/room/abc123").unwrap_err();
+        assert_eq!(err, AddressError::UnsupportedScheme(String::from("https")));
+        assert!(err.to_string().starts_with("room page links do not contain the port"));
+    }
+}
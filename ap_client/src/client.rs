@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use futures_util::lock::Mutex;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tungstenite::Message;
+
+use crate::address::AddressError;
+use crate::messages::{APClientMessage, APServerMessage};
+use crate::recording::Recorder;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Address(AddressError),
+    WebSocket(tungstenite::Error),
+    Serialize(serde_json::Error),
+    /// A frame that is not a list of server messages. The stream goes on
+    /// after it.
+    Parse { text: String, error: serde_json::Error },
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Address(err) => write!(f, "invalid server address: {}", err),
+            ClientError::WebSocket(err) => write!(f, "websocket error: {}", err),
+            ClientError::Serialize(err) => write!(f, "could not serialize message: {}", err),
+            ClientError::Parse { error, .. } => write!(f, "could not parse server message: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Address(err) => Some(err),
+            ClientError::WebSocket(err) => Some(err),
+            ClientError::Serialize(err) | ClientError::Parse { error: err, .. } => Some(err),
+        }
+    }
+}
+
+impl From<AddressError> for ClientError {
+    fn from(err: AddressError) -> Self {
+        ClientError::Address(err)
+    }
+}
+
+impl From<tungstenite::Error> for ClientError {
+    fn from(err: tungstenite::Error) -> Self {
+        ClientError::WebSocket(err)
+    }
+}
+
+/// Send half of a client, cheap to clone.
+pub struct Sender<T> {
+    sink: Arc<Mutex<SplitSink<T, Message>>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+impl<T> Sender<T>
+where
+    T: Sink<Message, Error = tungstenite::Error>,
+{
+    pub async fn send(&self, message: APClientMessage) -> Result<(), ClientError> {
+        self.send_all(&[message]).await
+    }
+
+    /// Sends the messages as a single frame.
+    pub async fn send_all(&self, messages: &[APClientMessage]) -> Result<(), ClientError> {
+        let text = serde_json::to_string(messages).map_err(ClientError::Serialize)?;
+        self.sink.lock().await.send(Message::Text(text)).await?;
+        Ok(())
+    }
+
+    /// Sends a close frame, the receiver ends once the server answers.
+    pub async fn close(&self) -> Result<(), ClientError> {
+        self.sink.lock().await.close().await?;
+        Ok(())
+    }
+}
+
+/// Receive half of a client, a stream of the messages sent by the server.
+/// Ends when the connection is closed.
+pub struct Receiver<T> {
+    stream: SplitStream<T>,
+    // A frame holds a list of messages, handed out one at a time.
+    pending: VecDeque<APServerMessage>,
+    recorder: Option<Recorder>,
+}
+
+impl<T> Receiver<T> {
+    /// Writes every text frame received from now on to `recorder`.
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+}
+
+/// Parses a text frame, which holds a list of server messages.
+pub fn parse_frame(text: &str) -> Result<Vec<APServerMessage>, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+impl<T> Stream for Receiver<T>
+where
+    T: Stream<Item = Result<Message, tungstenite::Error>>,
+{
+    type Item = Result<APServerMessage, ClientError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(message) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(message)));
+            }
+
+            match ready!(this.stream.poll_next_unpin(cx)) {
+                None | Some(Ok(Message::Close(_))) => return Poll::Ready(None),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                Some(Ok(Message::Text(text))) => {
+                    if let Some(recorder) = &this.recorder {
+                        recorder.record(&text);
+                    }
+                    match parse_frame(&text) {
+                        Ok(messages) => this.pending.extend(messages),
+                        Err(error) => return Poll::Ready(Some(Err(ClientError::Parse { text, error }))),
+                    }
+                }
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+}
+
+/// Wraps an established websocket connection.
+pub fn split<T>(transport: T) -> (Sender<T>, Receiver<T>)
+where
+    T: Stream<Item = Result<Message, tungstenite::Error>> + Sink<Message, Error = tungstenite::Error>,
+{
+    let (sink, stream) = transport.split();
+    (
+        Sender {
+            sink: Arc::new(Mutex::new(sink)),
+        },
+        Receiver {
+            stream,
+            pending: VecDeque::new(),
+            recorder: None,
+        },
+    )
+}
+
+#[cfg(feature = "tokio")]
+pub use self::tcp::{connect, connect_with, Connector, TcpTransport};
+
+#[cfg(feature = "tokio")]
+mod tcp {
+    use tokio::net::TcpStream;
+    pub use tokio_tungstenite::Connector;
+    use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+    use tracing::info;
+
+    use super::{split, ClientError, Receiver, Sender};
+    use crate::address::{Scheme, ServerAddress};
+
+    pub type TcpTransport = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    /// Opens a connection to the server. Without an explicit scheme `wss` is
+    /// tried first, falling back to `ws` if the server does not speak TLS.
+    pub async fn connect(
+        address: &ServerAddress,
+    ) -> Result<(Sender<TcpTransport>, Receiver<TcpTransport>), ClientError> {
+        connect_with(address, None).await
+    }
+
+    /// Same as [`connect`], with the TLS settings of `connector` instead of
+    /// the system defaults.
+    pub async fn connect_with(
+        address: &ServerAddress,
+        connector: Option<Connector>,
+    ) -> Result<(Sender<TcpTransport>, Receiver<TcpTransport>), ClientError> {
+        let open = |scheme| {
+            tokio_tungstenite::connect_async_tls_with_config(address.url(scheme), None, false, connector.clone())
+        };
+        let stream = match address.scheme {
+            Some(scheme) => open(scheme).await?.0,
+            None => match open(Scheme::Wss).await {
+                Ok((stream, _)) => stream,
+                Err(tungstenite::Error::Tls(_)) => open(Scheme::Ws).await?.0,
+                Err(err) => return Err(err.into()),
+            },
+        };
+        info!("Connected to {}", address);
+        Ok(split(stream))
+    }
+}
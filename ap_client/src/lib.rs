@@ -0,0 +1,19 @@
+//! Client for the [Archipelago](https://archipelago.gg) multiworld protocol.
+//!
+//! [`client::split`] turns any websocket transport into a [`Stream`] of
+//! server messages and a typed [`client::Sender`], without tying callers to
+//! an async runtime. The `tokio` feature adds [`client::connect`] and the
+//! reconnecting [`worker`], and the `mock` feature a fake server to test
+//! against. Sessions can be recorded and played back with [`recording`].
+//!
+//! [`Stream`]: futures_util::Stream
+
+pub mod address;
+pub mod client;
+pub mod messages;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod names;
+pub mod recording;
+#[cfg(feature = "tokio")]
+pub mod worker;
@@ -0,0 +1,463 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{de::Visitor, Deserialize, Serialize};
+
+// Server Message
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "cmd")]
+pub enum APServerMessage {
+    RoomInfo(RoomInfo),
+    ConnectionRefused(ConnectionRefused),
+    Connected(Connected),
+    ReceivedItems(ReceivedItems),
+    LocationInfo(()),
+    RoomUpdate(RoomUpdate),
+    PrintJSON(#[serde(deserialize_with = "deserialize_print_json")] PrintJSON),
+    DataPackage(DataPackage),
+    Bounced(()),
+    InvalidPacket(()),
+    Retrieved(Retrieved),
+    SetReply(SetReply),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoomInfo {
+    pub password: bool,
+    pub hint_cost: u32,
+    pub location_check_points: u32,
+    #[serde(default)]
+    pub seed_name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnectionRefused {
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Connected {
+    pub team: u32,
+    pub slot: u32,
+    pub players: Vec<NetworkPlayer>,
+    pub missing_locations: Vec<u32>,
+    pub checked_locations: Vec<u32>,
+    pub hint_points: u32,
+    // only sent if slot_data was requested in Connect
+    #[serde(default)]
+    pub slot_data: Option<serde_json::Value>,
+    #[serde(default, deserialize_with = "deserialize_slot_info")]
+    pub slot_info: BTreeMap<u32, NetworkSlot>,
+}
+
+// Slots are keyed by their number as a string. Messages are buffered to find
+// their `cmd` first, and buffered strings cannot be read as numbers.
+fn deserialize_slot_info<'de, D>(deserializer: D) -> Result<BTreeMap<u32, NetworkSlot>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    BTreeMap::<String, NetworkSlot>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(slot, info)| {
+            slot.parse()
+                .map(|slot| (slot, info))
+                .map_err(|_| serde::de::Error::invalid_value(serde::de::Unexpected::Str(&slot), &"a slot number"))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkPlayer {
+    pub team: u32,
+    pub slot: u32,
+    pub alias: String,
+    pub name: String,
+}
+
+// See https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md#networkslot
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkSlot {
+    pub name: String,
+    pub game: String,
+    pub r#type: SlotType,
+    pub group_members: Vec<u32>,
+}
+
+// See https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md#slottype
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotType {
+    Spectator,
+    Player,
+    Group,
+}
+
+impl std::fmt::Display for SlotType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SlotType::Spectator => f.write_str("Spectator"),
+            SlotType::Player => f.write_str("Player"),
+            SlotType::Group => f.write_str("Group"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SlotType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_u64(SlotTypeVisitor)
+    }
+}
+
+struct SlotTypeVisitor;
+
+impl<'de> Visitor<'de> for SlotTypeVisitor {
+    type Value = SlotType;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an integer between 0 and 2")
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match v {
+            0b00 => Ok(SlotType::Spectator),
+            0b01 => Ok(SlotType::Player),
+            0b10 => Ok(SlotType::Group),
+            _ => Err(E::invalid_value(serde::de::Unexpected::Unsigned(v), &self)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReceivedItems {
+    /// Position of the first item in the list of every item received.
+    pub index: u32,
+    pub items: Vec<NetworkItem>,
+}
+
+// See https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md#roomupdate
+// Only the fields that changed are sent.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoomUpdate {
+    #[serde(default)]
+    pub hint_points: Option<u32>,
+    #[serde(default)]
+    pub players: Option<Vec<NetworkPlayer>>,
+    #[serde(default)]
+    pub checked_locations: Option<Vec<u32>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+#[serde()]
+pub enum PrintJSON {
+    Text {
+        data: Vec<JSONMessagePart>,
+    },
+    ItemSend {
+        data: Vec<JSONMessagePart>,
+        receiving: u32,
+        item: NetworkItem,
+    },
+    ItemCheat {
+        data: Vec<JSONMessagePart>,
+        receiving: u32,
+        item: NetworkItem,
+        team: u32,
+    },
+    Hint {
+        data: Vec<JSONMessagePart>,
+        receiving: u32,
+        item: NetworkItem,
+        found: bool,
+    },
+    Join {
+        data: Vec<JSONMessagePart>,
+        team: u32,
+        slot: u32,
+        tags: Vec<String>,
+    },
+    Part {
+        data: Vec<JSONMessagePart>,
+        team: u32,
+        slot: u32,
+    },
+    Chat {
+        data: Vec<JSONMessagePart>,
+        team: u32,
+        slot: u32,
+        message: String,
+    },
+    ServerChat {
+        data: Vec<JSONMessagePart>,
+        message: String,
+    },
+    Tutorial {
+        data: Vec<JSONMessagePart>,
+    },
+    TagsChanged {
+        data: Vec<JSONMessagePart>,
+        team: u32,
+        slot: u32,
+        tags: Vec<String>,
+    },
+    CommandResult {
+        data: Vec<JSONMessagePart>,
+    },
+    AdminCommandResult {
+        data: Vec<JSONMessagePart>,
+    },
+    Goal {
+        data: Vec<JSONMessagePart>,
+        team: u32,
+        slot: u32,
+    },
+    Release {
+        data: Vec<JSONMessagePart>,
+        team: u32,
+        slot: u32,
+    },
+    Collect {
+        data: Vec<JSONMessagePart>,
+        team: u32,
+        slot: u32,
+    },
+    Countdown {
+        data: Vec<JSONMessagePart>,
+        countdown: u32,
+    },
+}
+
+// Plain text messages are sent without a type.
+fn deserialize_print_json<'de, D>(deserializer: D) -> Result<PrintJSON, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut value = serde_json::Value::deserialize(deserializer)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields
+            .entry("type")
+            .or_insert_with(|| serde_json::Value::String("Text".to_owned()));
+    }
+    PrintJSON::deserialize(value).map_err(serde::de::Error::custom)
+}
+
+impl PrintJSON {
+    pub fn data(&self) -> &[JSONMessagePart] {
+        match self {
+            PrintJSON::Text { data }
+            | PrintJSON::ItemSend { data, .. }
+            | PrintJSON::ItemCheat { data, .. }
+            | PrintJSON::Hint { data, .. }
+            | PrintJSON::Join { data, .. }
+            | PrintJSON::Part { data, .. }
+            | PrintJSON::Chat { data, .. }
+            | PrintJSON::ServerChat { data, .. }
+            | PrintJSON::Tutorial { data }
+            | PrintJSON::TagsChanged { data, .. }
+            | PrintJSON::CommandResult { data }
+            | PrintJSON::AdminCommandResult { data }
+            | PrintJSON::Goal { data, .. }
+            | PrintJSON::Release { data, .. }
+            | PrintJSON::Collect { data, .. }
+            | PrintJSON::Countdown { data, .. } => data,
+        }
+    }
+
+    /// The `type` the message was sent with.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PrintJSON::Text { .. } => "Text",
+            PrintJSON::ItemSend { .. } => "ItemSend",
+            PrintJSON::ItemCheat { .. } => "ItemCheat",
+            PrintJSON::Hint { .. } => "Hint",
+            PrintJSON::Join { .. } => "Join",
+            PrintJSON::Part { .. } => "Part",
+            PrintJSON::Chat { .. } => "Chat",
+            PrintJSON::ServerChat { .. } => "ServerChat",
+            PrintJSON::Tutorial { .. } => "Tutorial",
+            PrintJSON::TagsChanged { .. } => "TagsChanged",
+            PrintJSON::CommandResult { .. } => "CommandResult",
+            PrintJSON::AdminCommandResult { .. } => "AdminCommandResult",
+            PrintJSON::Goal { .. } => "Goal",
+            PrintJSON::Release { .. } => "Release",
+            PrintJSON::Collect { .. } => "Collect",
+            PrintJSON::Countdown { .. } => "Countdown",
+        }
+    }
+}
+
+// See https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md#datapackage
+#[derive(Debug, Clone, Deserialize)]
+pub struct DataPackage {
+    pub data: DataPackageObject,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DataPackageObject {
+    pub games: HashMap<String, GameData>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GameData {
+    pub item_name_to_id: HashMap<String, i64>,
+    pub location_name_to_id: HashMap<String, i64>,
+    #[serde(default)]
+    pub checksum: Option<String>,
+}
+
+// See https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md#retrieved
+#[derive(Debug, Clone, Deserialize)]
+pub struct Retrieved {
+    pub keys: BTreeMap<String, serde_json::Value>,
+}
+
+// See https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md#setreply
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetReply {
+    pub key: String,
+    pub value: serde_json::Value,
+    #[serde(default)]
+    pub original_value: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JSONMessagePart {
+    pub r#type: Option<String>,
+    pub text: Option<String>,
+    pub color: Option<String>, // only available if type is a color
+    pub flags: Option<u32>,    // only available if type is an item_id or item_name
+    pub player: Option<u32>,   // only available if type is either item or location
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkItem {
+    pub item: i64,
+    // negative for special locations, such as -1 for the cheat console
+    pub location: i64,
+    pub player: u32,
+    pub flags: ItemType,
+}
+
+// See https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md#networkitem
+#[derive(Debug, Clone)]
+
+pub enum ItemType {
+    Normal,
+    Logical,
+    Important,
+    Trap,
+}
+
+impl<'de> Deserialize<'de> for ItemType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_u64(ItemTypeVisitor)
+    }
+}
+
+struct ItemTypeVisitor;
+
+impl<'de> Visitor<'de> for ItemTypeVisitor {
+    type Value = ItemType;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an integer between 0 and 7")
+    }
+
+    // The flags can be combined, the most relevant one wins.
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match v {
+            _ if v > 0b111 => Err(E::invalid_value(serde::de::Unexpected::Unsigned(v), &self)),
+            _ if v & 0b001 != 0 => Ok(ItemType::Logical),
+            _ if v & 0b010 != 0 => Ok(ItemType::Important),
+            _ if v & 0b100 != 0 => Ok(ItemType::Trap),
+            _ => Ok(ItemType::Normal),
+        }
+    }
+}
+
+// Client Message
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "cmd")]
+pub enum APClientMessage {
+    Connect(Connect),
+    GetDataPackage(GetDataPackage),
+    Say(Say),
+    Get(Get),
+    SetNotify(SetNotify),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GetDataPackage {
+    // every game when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub games: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Say {
+    pub text: String,
+}
+
+// Answered with a `Retrieved`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Get {
+    pub keys: Vec<String>,
+}
+
+// Every later change of the keys is sent as a `SetReply`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SetNotify {
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+
+pub struct Connect {
+    pub name: String,
+    pub password: String,
+    pub game: String,
+    pub uuid: String,
+    pub version: Version,
+    pub items_handling: u32,
+    pub tags: Vec<String>,
+    pub slot_data: bool,
+}
+
+impl Default for Connect {
+    fn default() -> Self {
+        Self {
+            name: Default::default(),
+            password: Default::default(),
+            game: Default::default(),
+            uuid: Default::default(),
+            version: Version {
+                major: 5,
+                minor: 0,
+                build: 0,
+            },
+            items_handling: 0,
+            tags: ["Tracker".to_owned()].to_vec(),
+            slot_data: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "class")]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+}
@@ -0,0 +1,317 @@
+//! Scriptable Archipelago server running in-process, to test clients without
+//! a real multiworld.
+//!
+//! Every connection gets a `RoomInfo`, then the `Connect` it answers with is
+//! checked against the script's slots and password. Once connected, the
+//! script's steps are played in order.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::debug;
+use tungstenite::Message;
+
+use crate::client::Connector;
+
+/// What the server does once a client is connected.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Sends the messages as a single frame.
+    Send(Vec<Value>),
+    Wait(Duration),
+    /// Closes the socket with a close frame.
+    Close,
+    /// Drops the socket without a close frame, like a lost connection.
+    Drop,
+}
+
+/// Room played by a [`MockServer`], for every connection.
+#[derive(Debug, Clone)]
+pub struct Script {
+    /// Name and game of each slot, numbered from 1.
+    pub slots: Vec<(String, String)>,
+    /// Room password, `Connect` must match it when set.
+    pub password: Option<String>,
+    pub slot_data: Value,
+    pub steps: Vec<Step>,
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Self {
+            slots: vec![(String::from("Player1"), String::from("Clique"))],
+            password: None,
+            slot_data: json!({}),
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl Script {
+    fn room_info(&self) -> Value {
+        json!({
+            "cmd": "RoomInfo",
+            "version": { "major": 0, "minor": 5, "build": 0, "class": "Version" },
+            "generator_version": { "major": 0, "minor": 5, "build": 0, "class": "Version" },
+            "tags": ["AP"],
+            "password": self.password.is_some(),
+            "permissions": { "release": 2, "collect": 2, "remaining": 0 },
+            "hint_cost": 10,
+            "location_check_points": 1,
+            "games": self.slots.iter().map(|(_, game)| game).collect::<Vec<_>>(),
+            "datapackage_checksums": {},
+            "seed_name": "mock",
+            "time": 0.0,
+        })
+    }
+
+    /// Answer to a `Connect`, either `Connected` or `ConnectionRefused`.
+    fn answer(&self, connect: &Value) -> (Value, bool) {
+        let name = connect["name"].as_str().unwrap_or_default();
+        let password = connect["password"].as_str().unwrap_or_default();
+
+        let mut errors = Vec::new();
+        let slot = self.slots.iter().position(|(slot, _)| slot == name);
+        if slot.is_none() {
+            errors.push("InvalidSlot");
+        }
+        if self.password.as_deref().is_some_and(|expected| expected != password) {
+            errors.push("InvalidPassword");
+        }
+        let Some(slot) = slot.filter(|_| errors.is_empty()) else {
+            return (json!({ "cmd": "ConnectionRefused", "errors": errors }), false);
+        };
+
+        let players: Vec<Value> = self
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| json!({ "team": 0, "slot": index + 1, "alias": name, "name": name }))
+            .collect();
+        let slot_info: serde_json::Map<String, Value> = self
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(index, (name, game))| {
+                let info = json!({ "name": name, "game": game, "type": 1, "group_members": [] });
+                ((index + 1).to_string(), info)
+            })
+            .collect();
+        let slot_data = match connect["slot_data"].as_bool() {
+            Some(true) => self.slot_data.clone(),
+            _ => Value::Null,
+        };
+
+        let connected = json!({
+            "cmd": "Connected",
+            "team": 0,
+            "slot": slot + 1,
+            "players": players,
+            "missing_locations": [],
+            "checked_locations": [],
+            "slot_data": slot_data,
+            "slot_info": slot_info,
+            "hint_points": 0,
+        });
+        (connected, true)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Record {
+    connections: usize,
+    received: Vec<Value>,
+}
+
+/// A fake server listening on a free local port until dropped.
+pub struct MockServer {
+    address: String,
+    certificate: Option<native_tls::Certificate>,
+    record: Arc<Mutex<Record>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts a plain websocket server.
+    pub async fn start(script: Script) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = format!("ws://{}", listener.local_addr()?);
+        let record = Arc::new(Mutex::new(Record::default()));
+
+        let task = tokio::spawn({
+            let record = record.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, script.clone(), record.clone()));
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            certificate: None,
+            record,
+            task,
+        })
+    }
+
+    /// Starts a TLS websocket server with a self-signed certificate for
+    /// `localhost`, trusted by [`MockServer::connector`].
+    pub async fn start_tls(script: Script) -> io::Result<Self> {
+        let certified = rcgen::generate_simple_self_signed(vec![String::from("localhost")])
+            .map_err(io::Error::other)?;
+        let certificate_pem = certified.cert.pem();
+        let identity = native_tls::Identity::from_pkcs8(
+            certificate_pem.as_bytes(),
+            certified.key_pair.serialize_pem().as_bytes(),
+        )
+        .map_err(io::Error::other)?;
+        let acceptor = tokio_native_tls::TlsAcceptor::from(
+            native_tls::TlsAcceptor::new(identity).map_err(io::Error::other)?,
+        );
+        let certificate =
+            native_tls::Certificate::from_pem(certificate_pem.as_bytes()).map_err(io::Error::other)?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = format!("wss://localhost:{}", listener.local_addr()?.port());
+        let record = Arc::new(Mutex::new(Record::default()));
+
+        let task = tokio::spawn({
+            let record = record.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let acceptor = acceptor.clone();
+                    let script = script.clone();
+                    let record = record.clone();
+                    tokio::spawn(async move {
+                        match acceptor.accept(stream).await {
+                            Ok(stream) => serve(stream, script, record).await,
+                            Err(err) => debug!("TLS handshake failed: {}", err),
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            certificate: Some(certificate),
+            record,
+            task,
+        })
+    }
+
+    /// Address to connect to, scheme included.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// TLS settings trusting the server's certificate, `None` for plain
+    /// websocket servers.
+    pub fn connector(&self) -> Option<Connector> {
+        let certificate = self.certificate.clone()?;
+        let connector = native_tls::TlsConnector::builder()
+            .add_root_certificate(certificate)
+            .build()
+            .expect("the mock certificate is valid");
+        Some(Connector::NativeTls(connector))
+    }
+
+    /// Number of websocket connections accepted so far.
+    pub fn connections(&self) -> usize {
+        self.record.lock().unwrap().connections
+    }
+
+    /// Every message sent by clients, oldest first.
+    pub fn received(&self) -> Vec<Value> {
+        self.record.lock().unwrap().received.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve<S>(stream: S, script: Script, record: Arc<Mutex<Record>>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    record.lock().unwrap().connections += 1;
+
+    if socket
+        .send(Message::Text(json!([script.room_info()]).to_string()))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let mut steps: Option<VecDeque<Step>> = None;
+    let mut wait_until: Option<Instant> = None;
+    loop {
+        // Steps run back to back, only waits let the client's frames in.
+        if let (Some(pending), None) = (&mut steps, wait_until) {
+            match pending.pop_front() {
+                Some(Step::Send(messages)) => {
+                    if socket.send(Message::Text(Value::from(messages).to_string())).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                Some(Step::Wait(delay)) => wait_until = Some(Instant::now() + delay),
+                Some(Step::Close) => {
+                    let _ = socket.close(None).await;
+                }
+                Some(Step::Drop) => return,
+                None => {}
+            }
+        }
+
+        let frame = match wait_until {
+            Some(deadline) => tokio::select! {
+                frame = socket.next() => frame,
+                _ = tokio::time::sleep_until(deadline) => {
+                    wait_until = None;
+                    continue;
+                }
+            },
+            None => socket.next().await,
+        };
+
+        let text = match frame {
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(_)) => continue,
+            Some(Err(_)) | None => return,
+        };
+        let Ok(Value::Array(messages)) = serde_json::from_str(&text) else {
+            debug!("Invalid frame from client: {}", text);
+            continue;
+        };
+
+        for message in messages {
+            if message["cmd"] == "Connect" && steps.is_none() {
+                let (answer, accepted) = script.answer(&message);
+                if socket.send(Message::Text(json!([answer]).to_string())).await.is_err() {
+                    return;
+                }
+                if accepted {
+                    steps = Some(script.steps.iter().cloned().collect());
+                }
+            }
+            record.lock().unwrap().received.push(message);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use crate::messages::GameData;
+
+/// Item and location names of every game received through `DataPackage`.
+#[derive(Debug, Clone, Default)]
+pub struct Names {
+    items: HashMap<String, HashMap<i64, String>>,
+    locations: HashMap<String, HashMap<i64, String>>,
+}
+
+impl Names {
+    pub fn add(&mut self, games: &HashMap<String, GameData>) {
+        for (game, data) in games {
+            self.items.insert(game.clone(), invert(&data.item_name_to_id));
+            self.locations.insert(game.clone(), invert(&data.location_name_to_id));
+        }
+    }
+
+    pub fn has_game(&self, game: &str) -> bool {
+        self.items.contains_key(game)
+    }
+
+    pub fn item(&self, game: &str, id: i64) -> Option<&str> {
+        self.items.get(game)?.get(&id).map(String::as_str)
+    }
+
+    pub fn location(&self, game: &str, id: i64) -> Option<&str> {
+        self.locations.get(game)?.get(&id).map(String::as_str)
+    }
+}
+
+fn invert(name_to_id: &HashMap<String, i64>) -> HashMap<i64, String> {
+    name_to_id
+        .iter()
+        .map(|(name, id)| (*id, name.clone()))
+        .collect()
+}
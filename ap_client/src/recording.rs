@@ -0,0 +1,192 @@
+//! Recording the frames a server sends, and playing them back.
+//!
+//! A recording is a JSON Lines file, one [`Frame`] per line, so that it can
+//! be cut down by hand and attached to bug reports.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+/// A text frame received from the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    /// Milliseconds since the recording started.
+    pub at: u64,
+    pub text: String,
+}
+
+impl Frame {
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.at)
+    }
+}
+
+/// Appends received frames to a file, cheap to clone.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    file: Arc<Mutex<BufWriter<File>>>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            file: Arc::new(Mutex::new(BufWriter::new(File::create(path)?))),
+            start: Instant::now(),
+        })
+    }
+
+    /// Writes a frame, flushed right away so that a crash keeps it.
+    pub fn record(&self, text: &str) {
+        let frame = Frame {
+            at: self.start.elapsed().as_millis() as u64,
+            text: text.to_owned(),
+        };
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let written = serde_json::to_writer(&mut *file, &frame)
+            .map_err(io::Error::from)
+            .and_then(|_| file.write_all(b"\n"))
+            .and_then(|_| file.flush());
+        if let Err(err) = written {
+            error!("Could not record frame: {}", err);
+        }
+    }
+}
+
+/// Reads a recording, skipping blank lines.
+pub fn read(reader: impl BufRead) -> io::Result<Vec<Frame>> {
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Frame>> {
+    read(BufReader::new(File::open(path)?))
+}
+
+/// How fast a recording is played back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    /// Recorded delays divided by the factor, 1.0 being real time.
+    Scaled(f64),
+    /// One frame per [`InputMessage::Step`](crate::worker::InputMessage::Step).
+    Stepped,
+}
+
+#[cfg(feature = "tokio")]
+pub use self::replay::replay;
+
+#[cfg(feature = "tokio")]
+mod replay {
+    use std::convert::Infallible;
+
+    use futures_channel::mpsc;
+    use futures_util::{Sink, SinkExt, StreamExt};
+    use tokio::time::Instant;
+    use tracing::{debug, error};
+
+    use super::{Frame, Speed};
+    use crate::client::parse_frame;
+    use crate::worker::{Connection, Event, InputMessage};
+
+    /// Plays a recording as if a worker was connected to the server. Like
+    /// [`worker::run`](crate::worker::run) it starts with `WorkerReady` and
+    /// waits for a `Connect`, which restarts the recording from the start.
+    /// `Disconnect` stops it, and so does the end of the recording.
+    pub async fn replay<S>(frames: Vec<Frame>, speed: Speed, mut output: S) -> Infallible
+    where
+        S: Sink<Event> + Unpin,
+    {
+        let (sender, mut receiver) = mpsc::channel(100);
+        let _ = output.send(Event::WorkerReady(Connection(sender))).await;
+
+        // Index of the next frame, `None` while stopped.
+        let mut next: Option<usize> = None;
+        // When the previous frame was played, delays are counted from there.
+        let mut played_at = Instant::now();
+        loop {
+            let Some(index) = next else {
+                match receiver.next().await {
+                    Some(InputMessage::Connect(_)) => {
+                        next = Some(0);
+                        played_at = Instant::now();
+                    }
+                    Some(InputMessage::Disconnect) => {
+                        let _ = output.send(Event::Disconnected).await;
+                    }
+                    Some(_) => {}
+                    // Nobody left to start the replay again.
+                    None => std::future::pending::<()>().await,
+                }
+                continue;
+            };
+
+            let Some(frame) = frames.get(index) else {
+                next = None;
+                let _ = output.send(Event::Disconnected).await;
+                continue;
+            };
+
+            let ready = async {
+                match speed {
+                    Speed::Scaled(factor) => {
+                        let previous = index.checked_sub(1).map(|previous| frames[previous].time());
+                        let delay = frame.time().saturating_sub(previous.unwrap_or_default());
+                        let delay = delay.div_f64(factor.max(f64::MIN_POSITIVE));
+                        tokio::time::sleep_until(played_at + delay).await;
+                    }
+                    Speed::Stepped => std::future::pending::<()>().await,
+                }
+            };
+
+            tokio::select! {
+                _ = ready => {
+                    send_frame(frame, &mut output).await;
+                    next = Some(index + 1);
+                    played_at = Instant::now();
+                }
+                Some(input) = receiver.next() => match input {
+                    InputMessage::Connect(_) => {
+                        let _ = output.send(Event::Disconnected).await;
+                        next = Some(0);
+                        played_at = Instant::now();
+                    }
+                    InputMessage::Step => {
+                        send_frame(frame, &mut output).await;
+                        next = Some(index + 1);
+                        played_at = Instant::now();
+                    }
+                    InputMessage::Send(_) => debug!("Replaying, dropping message"),
+                    InputMessage::Disconnect => {
+                        next = None;
+                        let _ = output.send(Event::Disconnected).await;
+                    }
+                },
+            }
+        }
+    }
+
+    async fn send_frame<S>(frame: &Frame, output: &mut S)
+    where
+        S: Sink<Event> + Unpin,
+    {
+        match parse_frame(&frame.text) {
+            Ok(messages) => {
+                for message in messages {
+                    let _ = output.send(Event::APMessage(message)).await;
+                }
+            }
+            Err(err) => {
+                error!("Failed converting to APMessage {:?}", err);
+                let _ = output.send(Event::ParseFailed).await;
+            }
+        }
+    }
+}
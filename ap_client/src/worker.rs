@@ -0,0 +1,200 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use futures_channel::mpsc;
+use futures_util::{Sink, SinkExt, StreamExt};
+use tracing::{debug, error};
+
+use crate::address::ServerAddress;
+use crate::client::{self, ClientError, Connector, Receiver, Sender, TcpTransport};
+use crate::messages::{APClientMessage, APServerMessage, Connect};
+use crate::recording::Recorder;
+
+/// Time between two connection attempts.
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How a worker connects, [`run`] uses the defaults.
+#[derive(Clone)]
+pub struct Options {
+    pub reconnect_delay: Duration,
+    /// TLS settings, the system defaults when `None`.
+    pub connector: Option<Connector>,
+    /// Records the frames of every connection, one after the other.
+    pub recorder: Option<Recorder>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            reconnect_delay: RECONNECT_DELAY,
+            connector: None,
+            recorder: None,
+        }
+    }
+}
+
+/// Where to connect, and the `Connect` packet to answer `RoomInfo` with.
+#[derive(Debug, Clone)]
+pub struct ConnectRequest {
+    pub server: String,
+    pub connect: Connect,
+}
+
+#[derive(Debug)]
+pub enum InputMessage {
+    Connect(ConnectRequest),
+    /// Dropped unless connected.
+    Send(Vec<APClientMessage>),
+    /// Closes the socket and stops reconnecting until the next `Connect`.
+    Disconnect,
+    /// Plays the next frame of a stepped replay, ignored by live workers.
+    Step,
+}
+
+/// Handle used to control a running worker.
+#[derive(Debug, Clone)]
+pub struct Connection(pub mpsc::Sender<InputMessage>);
+
+impl Connection {
+    pub fn send(&mut self, message: InputMessage) {
+        if let Err(err) = self.0.try_send(message) {
+            error!("Could not send message to worker: {}", err);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    WorkerReady(Connection),
+    APMessage(APServerMessage),
+    /// A message from the server could not be read and was skipped.
+    ParseFailed,
+    /// The socket closed, either on request or because it dropped.
+    Disconnected,
+}
+
+enum State {
+    Disconnected,
+    Connected(Sender<TcpTransport>, Box<Receiver<TcpTransport>>),
+}
+
+async fn open(
+    request: &ConnectRequest,
+    options: &Options,
+) -> Result<(Sender<TcpTransport>, Receiver<TcpTransport>), ClientError> {
+    let address: ServerAddress = request.server.parse()?;
+    let (sender, mut receiver) = client::connect_with(&address, options.connector.clone()).await?;
+    if let Some(recorder) = &options.recorder {
+        receiver.record(recorder.clone());
+    }
+    Ok((sender, receiver))
+}
+
+/// Keeps a connection to a room open, reconnecting until told to
+/// disconnect. The first event is always `WorkerReady`, carrying the handle
+/// used to send it a `Connect`.
+pub async fn run<S>(output: S) -> Infallible
+where
+    S: Sink<Event> + Unpin,
+{
+    run_with(output, Options::default()).await
+}
+
+/// Same as [`run`], connecting with `options`.
+pub async fn run_with<S>(mut output: S, options: Options) -> Infallible
+where
+    S: Sink<Event> + Unpin,
+{
+    let mut state = State::Disconnected;
+    let mut request: Option<ConnectRequest> = None;
+
+    let (sender, mut receiver) = mpsc::channel(100);
+
+    let _ = output.send(Event::WorkerReady(Connection(sender))).await;
+
+    loop {
+        match &mut state {
+            State::Disconnected => {
+                if let Some(request) = &request {
+                    match open(request, &options).await {
+                        Err(err) => {
+                            error!("{}", err);
+                        }
+                        Ok((server_sender, server_receiver)) => {
+                            state = State::Connected(server_sender, Box::new(server_receiver));
+                            continue;
+                        }
+                    }
+                }
+
+                tokio::select! {
+                    Some(input) = receiver.next() => {
+                        match input {
+                            InputMessage::Connect(updated) => request = Some(updated),
+                            InputMessage::Send(_) => error!("Not connected, dropping message"),
+                            InputMessage::Step => {}
+                            InputMessage::Disconnect => {
+                                request = None;
+                                let _ = output.send(Event::Disconnected).await;
+                            }
+                        }
+                    }
+
+                    _ = tokio::time::sleep(options.reconnect_delay) => {}
+                }
+            }
+            State::Connected(server_sender, server_receiver) => {
+                tokio::select! {
+                    message = server_receiver.next() => {
+                        match message {
+                            Some(Ok(message @ APServerMessage::RoomInfo(_))) => {
+                                if let Some(request) = &request {
+                                    let connect = APClientMessage::Connect(request.connect.clone());
+                                    if let Err(err) = server_sender.send(connect).await {
+                                        error!("{}", err);
+                                    }
+                                }
+                                let _ = output.send(Event::APMessage(message)).await;
+                            }
+                            Some(Ok(message)) => {
+                                debug!("{:?}", message);
+                                let _ = output.send(Event::APMessage(message)).await;
+                            }
+                            Some(Err(err @ ClientError::Parse { .. })) => {
+                                error!("Failed converting to APMessage {:?}", err);
+                                let _ = output.send(Event::ParseFailed).await;
+                            }
+                            Some(Err(_)) | None => {
+                                state = State::Disconnected;
+                                let _ = output.send(Event::Disconnected).await;
+                            }
+                        }
+                    }
+
+                    Some(input) = receiver.next() => {
+                        match input {
+                            InputMessage::Connect(updated) => {
+                                request = Some(updated);
+                                state = State::Disconnected;
+                            }
+                            InputMessage::Send(messages) => {
+                                if let Err(err) = server_sender.send_all(&messages).await {
+                                    error!("{}", err);
+                                }
+                            }
+                            InputMessage::Step => {}
+                            InputMessage::Disconnect => {
+                                if let Err(err) = server_sender.close().await {
+                                    error!("{}", err);
+                                }
+                                request = None;
+                                state = State::Disconnected;
+                                let _ = output.send(Event::Disconnected).await;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use ap_client::client::{self, Connector};
+use ap_client::messages::{APClientMessage, APServerMessage, Connect, PrintJSON, Say};
+use ap_client::mock::{MockServer, Script, Step};
+use ap_client::worker::{self, ConnectRequest, Connection, Event, InputMessage, Options};
+use futures_channel::mpsc;
+use futures_util::StreamExt;
+use serde_json::json;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn connect(name: &str, password: &str) -> Connect {
+    Connect {
+        name: name.to_owned(),
+        password: password.to_owned(),
+        tags: vec![String::from("Tracker")],
+        slot_data: true,
+        ..Default::default()
+    }
+}
+
+fn item_send() -> serde_json::Value {
+    json!({
+        "cmd": "PrintJSON",
+        "type": "ItemSend",
+        "data": [{ "text": "Player1 found their Sword" }],
+        "receiving": 1,
+        "item": { "item": 77, "location": 12, "player": 1, "flags": 1 },
+    })
+}
+
+/// Starts a worker connecting to `server`, returning its handle and events
+/// past `WorkerReady`.
+async fn start_worker(
+    server: &MockServer,
+    connect: Connect,
+    reconnect_delay: Duration,
+) -> (Connection, mpsc::Receiver<Event>) {
+    let (output, mut events) = mpsc::channel(100);
+    let options = Options {
+        reconnect_delay,
+        connector: server.connector(),
+        ..Default::default()
+    };
+    tokio::spawn(worker::run_with(output, options));
+
+    let Some(Event::WorkerReady(mut connection)) = next(&mut events).await else {
+        panic!("the first event is not WorkerReady");
+    };
+    connection.send(InputMessage::Connect(ConnectRequest {
+        server: server.address().to_owned(),
+        connect,
+    }));
+    (connection, events)
+}
+
+async fn next<T>(events: &mut mpsc::Receiver<T>) -> Option<T> {
+    tokio::time::timeout(TIMEOUT, events.next())
+        .await
+        .expect("timed out waiting for an event")
+}
+
+async fn next_message(events: &mut mpsc::Receiver<Event>) -> APServerMessage {
+    match next(events).await {
+        Some(Event::APMessage(message)) => message,
+        other => panic!("expected a server message, got {:?}", other),
+    }
+}
+
+async fn client_round_trip(server: &MockServer, connector: Option<Connector>) {
+    let address = server.address().parse().unwrap();
+    let (sender, mut receiver) = client::connect_with(&address, connector).await.unwrap();
+
+    let room_info = receiver.next().await.unwrap().unwrap();
+    assert!(matches!(room_info, APServerMessage::RoomInfo(_)));
+
+    sender
+        .send(APClientMessage::Connect(connect("Player1", "")))
+        .await
+        .unwrap();
+    let Some(Ok(APServerMessage::Connected(connected))) = receiver.next().await else {
+        panic!("not connected");
+    };
+    assert_eq!(connected.slot, 1);
+    assert_eq!(connected.slot_info[&1].game, "Clique");
+    assert_eq!(connected.slot_data, Some(json!({ "goal": 2 })));
+
+    let Some(Ok(APServerMessage::PrintJSON(PrintJSON::ItemSend { item, .. }))) = receiver.next().await else {
+        panic!("expected an ItemSend");
+    };
+    assert_eq!(item.item, 77);
+    let Some(Ok(APServerMessage::ReceivedItems(received))) = receiver.next().await else {
+        panic!("expected ReceivedItems");
+    };
+    assert_eq!(received.items.len(), 1);
+    let Some(Ok(APServerMessage::RoomUpdate(update))) = receiver.next().await else {
+        panic!("expected a RoomUpdate");
+    };
+    assert_eq!(update.hint_points, Some(5));
+
+    // The server closes the socket at the end of the script.
+    assert!(receiver.next().await.is_none());
+}
+
+fn room_script() -> Script {
+    Script {
+        slot_data: json!({ "goal": 2 }),
+        steps: vec![
+            Step::Send(vec![item_send()]),
+            Step::Send(vec![
+                json!({
+                    "cmd": "ReceivedItems",
+                    "index": 0,
+                    "items": [{ "item": 77, "location": 12, "player": 1, "flags": 1 }],
+                }),
+                json!({ "cmd": "RoomUpdate", "hint_points": 5 }),
+            ]),
+            Step::Close,
+        ],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn connects_over_ws() {
+    let server = MockServer::start(room_script()).await.unwrap();
+    client_round_trip(&server, None).await;
+}
+
+#[tokio::test]
+async fn connects_over_tls() {
+    let server = MockServer::start_tls(room_script()).await.unwrap();
+    assert!(server.address().starts_with("wss://"));
+    client_round_trip(&server, server.connector()).await;
+}
+
+#[tokio::test]
+async fn rejects_untrusted_certificate() {
+    let server = MockServer::start_tls(room_script()).await.unwrap();
+    let address = server.address().parse().unwrap();
+    assert!(client::connect(&address).await.is_err());
+}
+
+#[tokio::test]
+async fn worker_connects_and_sends() {
+    let server = MockServer::start(Script {
+        password: Some(String::from("secret")),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let (mut connection, mut events) =
+        start_worker(&server, connect("Player1", "secret"), Duration::from_secs(60)).await;
+
+    assert!(matches!(next_message(&mut events).await, APServerMessage::RoomInfo(_)));
+    assert!(matches!(next_message(&mut events).await, APServerMessage::Connected(_)));
+
+    connection.send(InputMessage::Send(vec![APClientMessage::Say(Say {
+        text: String::from("hello"),
+    })]));
+    tokio::time::timeout(TIMEOUT, async {
+        while !server.received().iter().any(|message| message["cmd"] == "Say") {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the server never got the Say");
+
+    let received = server.received();
+    assert_eq!(received[0]["cmd"], "Connect");
+    assert_eq!(received[1], json!({ "cmd": "Say", "text": "hello" }));
+}
+
+#[tokio::test]
+async fn worker_reports_refused_connection() {
+    let server = MockServer::start(Script {
+        password: Some(String::from("secret")),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let (_connection, mut events) =
+        start_worker(&server, connect("Nobody", "wrong"), Duration::from_secs(60)).await;
+
+    assert!(matches!(next_message(&mut events).await, APServerMessage::RoomInfo(_)));
+    let APServerMessage::ConnectionRefused(refused) = next_message(&mut events).await else {
+        panic!("the connection was not refused");
+    };
+    assert_eq!(refused.errors, ["InvalidSlot", "InvalidPassword"]);
+}
+
+#[tokio::test]
+async fn worker_stops_on_disconnect() {
+    let server = MockServer::start(Script::default()).await.unwrap();
+    let (mut connection, mut events) =
+        start_worker(&server, connect("Player1", ""), Duration::from_millis(50)).await;
+    assert!(matches!(next_message(&mut events).await, APServerMessage::RoomInfo(_)));
+    assert!(matches!(next_message(&mut events).await, APServerMessage::Connected(_)));
+
+    connection.send(InputMessage::Disconnect);
+    assert!(matches!(next(&mut events).await, Some(Event::Disconnected)));
+
+    // Well past the reconnect delay, nothing happens.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(server.connections(), 1);
+    assert!(events.try_next().is_err());
+}
+
+#[tokio::test]
+async fn worker_reconnects_after_lost_connection() {
+    let server = MockServer::start(Script {
+        steps: vec![Step::Send(vec![item_send()]), Step::Drop],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let (_connection, mut events) =
+        start_worker(&server, connect("Player1", ""), Duration::from_millis(50)).await;
+
+    for _ in 0..2 {
+        assert!(matches!(next_message(&mut events).await, APServerMessage::RoomInfo(_)));
+        assert!(matches!(next_message(&mut events).await, APServerMessage::Connected(_)));
+        assert!(matches!(next_message(&mut events).await, APServerMessage::PrintJSON(_)));
+        assert!(matches!(next(&mut events).await, Some(Event::Disconnected)));
+    }
+    assert!(server.connections() >= 2);
+}
@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ap_client::messages::{APServerMessage, Connect};
+use ap_client::mock::{MockServer, Script, Step};
+use ap_client::recording::{self, Recorder, Speed};
+use ap_client::worker::{self, ConnectRequest, Connection, Event, InputMessage, Options};
+use futures_channel::mpsc;
+use futures_util::StreamExt;
+use serde_json::json;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn temporary_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ap_client-{}-{}.jsonl", std::process::id(), name))
+}
+
+fn connect_request(server: &str) -> ConnectRequest {
+    ConnectRequest {
+        server: server.to_owned(),
+        connect: Connect {
+            name: String::from("Player1"),
+            tags: vec![String::from("Tracker")],
+            ..Default::default()
+        },
+    }
+}
+
+async fn next(events: &mut mpsc::Receiver<Event>) -> Event {
+    tokio::time::timeout(TIMEOUT, events.next())
+        .await
+        .expect("timed out waiting for an event")
+        .expect("the worker stopped")
+}
+
+async fn ready(events: &mut mpsc::Receiver<Event>) -> Connection {
+    match next(events).await {
+        Event::WorkerReady(connection) => connection,
+        other => panic!("the first event is not WorkerReady: {:?}", other),
+    }
+}
+
+/// Server messages up to the next `Disconnected`, as text since messages
+/// cannot be compared.
+async fn messages_until_disconnected(events: &mut mpsc::Receiver<Event>) -> Vec<String> {
+    let mut messages = Vec::new();
+    loop {
+        match next(events).await {
+            Event::APMessage(message) => messages.push(format!("{:?}", message)),
+            Event::Disconnected => return messages,
+            Event::WorkerReady(_) => panic!("unexpected WorkerReady"),
+            Event::ParseFailed => panic!("unexpected ParseFailed"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn replays_what_was_recorded() {
+    let server = MockServer::start(Script {
+        steps: vec![
+            Step::Send(vec![json!({ "cmd": "PrintJSON", "data": [{ "text": "Welcome" }] })]),
+            Step::Wait(Duration::from_millis(100)),
+            Step::Send(vec![json!({ "cmd": "RoomUpdate", "hint_points": 3 })]),
+            Step::Close,
+        ],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let path = temporary_path("replays_what_was_recorded");
+
+    let (output, mut events) = mpsc::channel(100);
+    let options = Options {
+        recorder: Some(Recorder::create(&path).unwrap()),
+        reconnect_delay: Duration::from_secs(60),
+        ..Default::default()
+    };
+    tokio::spawn(worker::run_with(output, options));
+    ready(&mut events)
+        .await
+        .send(InputMessage::Connect(connect_request(server.address())));
+    let live = messages_until_disconnected(&mut events).await;
+    assert_eq!(live.len(), 4, "RoomInfo, Connected and the two steps");
+
+    let frames = recording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(frames.len(), 4, "RoomInfo, Connected and the two steps");
+    assert!(frames[3].at >= 100, "the wait is recorded");
+
+    let (output, mut events) = mpsc::channel(100);
+    tokio::spawn(recording::replay(frames, Speed::Scaled(10.0), output));
+    ready(&mut events)
+        .await
+        .send(InputMessage::Connect(connect_request(server.address())));
+    assert_eq!(messages_until_disconnected(&mut events).await, live);
+}
+
+#[tokio::test]
+async fn steps_through_a_recording() {
+    let frames = recording::load("tests/recordings/slot_info.jsonl".as_ref()).unwrap();
+    let (output, mut events) = mpsc::channel(100);
+    tokio::spawn(recording::replay(frames, Speed::Stepped, output));
+
+    let mut connection = ready(&mut events).await;
+    connection.send(InputMessage::Connect(connect_request("localhost")));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(events.try_next().is_err(), "nothing is played before the first step");
+
+    connection.send(InputMessage::Step);
+    assert!(matches!(next(&mut events).await, Event::APMessage(APServerMessage::RoomInfo(_))));
+    connection.send(InputMessage::Step);
+    assert!(matches!(next(&mut events).await, Event::APMessage(APServerMessage::Connected(_))));
+    connection.send(InputMessage::Step);
+    assert!(matches!(next(&mut events).await, Event::APMessage(APServerMessage::PrintJSON(_))));
+    connection.send(InputMessage::Step);
+    assert!(matches!(next(&mut events).await, Event::Disconnected));
+}
+
+// Slot numbers are strings in `slot_info`, which failed to parse.
+#[tokio::test]
+async fn regression_slot_info_keys() {
+    let frames = recording::load("tests/recordings/slot_info.jsonl".as_ref()).unwrap();
+    let (output, mut events) = mpsc::channel(100);
+    tokio::spawn(recording::replay(frames, Speed::Scaled(1000.0), output));
+    ready(&mut events)
+        .await
+        .send(InputMessage::Connect(connect_request("localhost")));
+
+    let Event::APMessage(APServerMessage::RoomInfo(room_info)) = next(&mut events).await else {
+        panic!("expected RoomInfo");
+    };
+    assert_eq!(room_info.seed_name, "regression");
+    let Event::APMessage(APServerMessage::Connected(connected)) = next(&mut events).await else {
+        panic!("expected Connected");
+    };
+    assert_eq!(connected.slot_info[&2].game, "A Link to the Past");
+}
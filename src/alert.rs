@@ -0,0 +1,136 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ap::messages::{Connected, PrintJSON};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    ItemReceived,
+    ItemSent,
+    Hint,
+    Goal,
+    Release,
+    /// An item of the watchlist was sent to the slot, found in its world or
+    /// hinted.
+    Watched,
+    /// A chat message named the slot or a keyword.
+    Mention,
+}
+
+impl AlertKind {
+    pub const ALL: [AlertKind; 7] = [
+        AlertKind::ItemReceived,
+        AlertKind::ItemSent,
+        AlertKind::Hint,
+        AlertKind::Goal,
+        AlertKind::Release,
+        AlertKind::Watched,
+        AlertKind::Mention,
+    ];
+
+    /// Urgent alerts are raised during do not disturb and quiet hours, and
+    /// are not rate limited.
+    pub fn is_urgent(self) -> bool {
+        self == AlertKind::Watched
+    }
+}
+
+impl std::fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertKind::ItemReceived => f.write_str("Item received"),
+            AlertKind::ItemSent => f.write_str("Item sent"),
+            AlertKind::Hint => f.write_str("Hint"),
+            AlertKind::Goal => f.write_str("Goal"),
+            AlertKind::Release => f.write_str("Release / Collect"),
+            AlertKind::Watched => f.write_str("Watched item"),
+            AlertKind::Mention => f.write_str("Chat mention"),
+        }
+    }
+}
+
+/// Which events raise an alert, saved with each profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertRules {
+    pub item_received: bool,
+    pub item_sent: bool,
+    pub hint: bool,
+    pub goal: bool,
+    pub release: bool,
+    pub watched: bool,
+    pub mention: bool,
+}
+
+impl Default for AlertRules {
+    fn default() -> Self {
+        Self {
+            item_received: true,
+            item_sent: false,
+            hint: true,
+            goal: true,
+            release: false,
+            watched: true,
+            mention: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub kind: AlertKind,
+    /// Label of the session the alert came from.
+    pub source: String,
+    pub text: String,
+    /// When the alert was raised.
+    pub at: Instant,
+    /// Index of the message that raised the alert in the log of its session,
+    /// see [`Session::log_position`](crate::session::Session::log_position).
+    /// Unset for alerts summing up several messages.
+    pub log_index: Option<u64>,
+}
+
+impl AlertRules {
+    pub fn get(&self, kind: AlertKind) -> bool {
+        match kind {
+            AlertKind::ItemReceived => self.item_received,
+            AlertKind::ItemSent => self.item_sent,
+            AlertKind::Hint => self.hint,
+            AlertKind::Goal => self.goal,
+            AlertKind::Release => self.release,
+            AlertKind::Watched => self.watched,
+            AlertKind::Mention => self.mention,
+        }
+    }
+
+    pub fn set(&mut self, kind: AlertKind, enabled: bool) {
+        match kind {
+            AlertKind::ItemReceived => self.item_received = enabled,
+            AlertKind::ItemSent => self.item_sent = enabled,
+            AlertKind::Hint => self.hint = enabled,
+            AlertKind::Goal => self.goal = enabled,
+            AlertKind::Release => self.release = enabled,
+            AlertKind::Watched => self.watched = enabled,
+            AlertKind::Mention => self.mention = enabled,
+        }
+    }
+
+    /// Kind of alert raised by the message for the slot in `room`, if the
+    /// rules enable it.
+    pub fn evaluate(&self, room: &Connected, message: &PrintJSON) -> Option<AlertKind> {
+        let kind = match message {
+            PrintJSON::ItemSend { receiving, .. } if *receiving == room.slot => AlertKind::ItemReceived,
+            PrintJSON::ItemSend { item, .. } if item.player == room.slot => AlertKind::ItemSent,
+            PrintJSON::Hint {
+                receiving, item, found: false, ..
+            } if *receiving == room.slot || item.player == room.slot => AlertKind::Hint,
+            PrintJSON::Goal { .. } => AlertKind::Goal,
+            PrintJSON::Release { .. } | PrintJSON::Collect { .. } => AlertKind::Release,
+            _ => return None,
+        };
+
+        self.get(kind).then_some(kind)
+    }
+}
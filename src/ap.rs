@@ -1,2 +1,3 @@
 pub mod connection;
-pub mod messages;
\ No newline at end of file
+
+pub use ap_client::{address, messages};
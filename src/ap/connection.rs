@@ -1,174 +1,132 @@
-use std::time::Duration;
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use futures_util::{select, FutureExt, SinkExt, StreamExt};
+use futures_util::{future, SinkExt};
+use tracing::error;
 
+use ap_client::recording::{self, Frame, Recorder};
+use ap_client::worker::{self, ConnectRequest};
 use iced::{futures::channel::mpsc, subscription};
 use serde::{Deserialize, Serialize};
-use tokio::net::TcpStream;
-use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
-use tracing::{debug, error, info};
 
-use crate::ap::messages::{APClientMessage, Connect};
+use crate::ap::address::DEFAULT_PORT;
+use crate::ap::messages::Connect;
 
-use super::messages::APServerMessage;
+pub use ap_client::recording::Speed;
+pub use ap_client::worker::{Connection, Event, InputMessage};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ConnectionInfo {
-    pub ip: String,
-    pub port: String,
+    pub server: String,
     pub slot: String,
+    /// Only written to the config by saves that predate the secret store.
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub password: String,
+    pub options: ConnectOptions,
 }
 
-impl Default for ConnectionInfo {
+/// The parts of the `Connect` packet that are not credentials.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectOptions {
+    pub game: String,
+    pub items_handling: u32,
+    pub tags: Vec<String>,
+    pub slot_data: bool,
+}
+
+impl Default for ConnectOptions {
     fn default() -> Self {
+        let connect = Connect::default();
         Self {
-            ip: "127.0.0.1".to_owned(),
-            port: "38281".to_owned(),
-            slot: Default::default(),
-            password: Default::default(),
+            game: connect.game,
+            items_handling: connect.items_handling,
+            tags: connect.tags,
+            slot_data: connect.slot_data,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum Event {
-    WorkerReady(Connection),
-    APMessage(super::messages::APServerMessage),
+impl ConnectionInfo {
+    pub fn to_request(&self) -> ConnectRequest {
+        ConnectRequest {
+            server: self.server.clone(),
+            connect: Connect {
+                name: self.slot.clone(),
+                password: self.password.clone(),
+                game: self.options.game.clone(),
+                items_handling: self.options.items_handling,
+                tags: self
+                    .options
+                    .tags
+                    .iter()
+                    .map(|tag| tag.trim().to_owned())
+                    .filter(|tag| !tag.is_empty())
+                    .collect(),
+                slot_data: self.options.slot_data,
+                ..Default::default()
+            },
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct Connection(pub mpsc::Sender<InputMessage>);
+// See https://github.com/ArchipelagoMW/Archipelago/blob/main/docs/network%20protocol.md#items_handling-flags
+pub const ITEMS_HANDLING_OTHER_WORLDS: u32 = 0b001;
+pub const ITEMS_HANDLING_OWN_WORLD: u32 = 0b010;
+pub const ITEMS_HANDLING_STARTING_INVENTORY: u32 = 0b100;
 
-impl Connection {
-    pub fn send(&mut self, message: InputMessage) {
-        self.0
-            .try_send(message)
-            .expect("Send message to echo server");
+impl Default for ConnectionInfo {
+    fn default() -> Self {
+        Self {
+            server: format!("127.0.0.1:{}", DEFAULT_PORT),
+            slot: Default::default(),
+            password: Default::default(),
+            options: Default::default(),
+        }
     }
 }
 
-pub enum InputMessage {
-    Connect(ConnectionInfo),
-}
+/// Identifies one worker, and so one connection to a room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConnectionId(pub u64);
 
-enum State {
-    Disconnected,
-    Connected(WebSocketStream<MaybeTlsStream<TcpStream>>),
+/// What feeds a session its events.
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// A worker connected to the server, recording its frames to the file
+    /// when set.
+    Live { record: Option<PathBuf> },
+    /// A recorded session played back.
+    Replay { frames: Arc<Vec<Frame>>, speed: Speed },
 }
 
-pub fn connect() -> iced::Subscription<Event> {
+pub fn connect(id: ConnectionId, source: Source) -> iced::Subscription<(ConnectionId, Event)> {
     struct WS;
 
-    subscription::channel(std::any::TypeId::of::<WS>(), 100, |mut output| async move {
-        let mut state = State::Disconnected;
-        let mut connection_info = None;
-
-        let (sender, mut receiver) = mpsc::channel(100);
-
-        let _ = output.send(Event::WorkerReady(Connection(sender))).await;
-
-        loop {
-            match &mut state {
-                State::Disconnected => {
-                    if let Some(connection_info) = &connection_info {
-                        match connect_to_ws(connection_info).await {
-                            Err(err) => {
-                                error!("{}", err);
-                            }
-                            Ok(server) => {
-                                state = State::Connected(server);
-                                continue;
-                            }
-                        }
-                    }
-
-                    select! {
-                        input = receiver.select_next_some() => {
-                            match input {
-                                InputMessage::Connect(info) => connection_info = Some(info),
-                            };
-                        }
-
-                        _ = tokio::time::sleep(Duration::new(5, 0)).fuse() => {}
-                    }
-                }
-                State::Connected(server) => {
-                    let mut fused_websocket = server.by_ref().fuse();
-
-                    select! {
-                        message = fused_websocket.select_next_some() => {
-                            match message {
-                                Ok(Message::Text(t)) => {
-                                    match serde_json::from_str::<Vec<APServerMessage>>(&t) {
-                                        Err(err) => error!("Failed converting to APMessage {:?}", err),
-                                        Ok(messages) => {
-                                            for message in messages {
-                                                debug!("{:?}", message);
-                                                match message {
-                                                    APServerMessage::RoomInfo(_) => {
-                                                        if let Some(info) = &connection_info {
-                                                            let message = APClientMessage::Connect(Connect {
-                                                                name: info.slot.clone(),
-                                                                password: info.password.clone(),
-                                                                ..Default::default()
-                                                            });
-                                                            if let Err(err) = fused_websocket.send(Message::Text(serde_json::to_string(&[message]).unwrap())).await {
-                                                                error!("{}", err);
-                                                            }
-                                                        }
-                                                    },
-                                                    _ => {
-                                                        let _ = output.send(Event::APMessage(message)).await;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                },
-                                Err(_) => state = State::Disconnected,
-                                Ok(_) => {},
-                            }
-                        }
+    subscription::channel((std::any::TypeId::of::<WS>(), id), 100, move |output| run(id, source, output))
+}
 
-                        gui_event = receiver.select_next_some() => {
-                            match gui_event {
-                                InputMessage::Connect(info) => {
-                                    connection_info.replace(info);
-                                    state = State::Disconnected;
-                                },
-                            }
-                        }
-                    }
+/// Runs a worker, tagging its events with `id`. Runs inside an iced
+/// subscription, or on its own in headless mode.
+pub async fn run(id: ConnectionId, source: Source, output: mpsc::Sender<(ConnectionId, Event)>) -> Infallible {
+    let output = output.with(move |event| future::ok::<_, mpsc::SendError>((id, event)));
+    match source {
+        Source::Live { record } => {
+            let recorder = record.and_then(|path| match Recorder::create(&path) {
+                Ok(recorder) => Some(recorder),
+                Err(err) => {
+                    error!("Could not record to {}: {}", path.display(), err);
+                    None
                 }
-            }
+            });
+            let options = worker::Options {
+                recorder,
+                ..Default::default()
+            };
+            worker::run_with(output, options).await
         }
-    })
+        Source::Replay { frames, speed } => recording::replay(frames.to_vec(), speed, output).await,
+    }
 }
-
-async fn connect_to_ws(
-    connection_info: &ConnectionInfo,
-) -> anyhow::Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
-    let stream = match tokio_tungstenite::connect_async(format!(
-        "wss://{}:{}",
-        connection_info.ip, connection_info.port
-    ))
-    .await
-    {
-        Ok((stream, _)) => stream,
-        Err(err) => {
-            if let tokio_tungstenite::tungstenite::Error::Tls(_) = err {
-                let (stream, _) = tokio_tungstenite::connect_async(format!(
-                    "ws://{}:{}",
-                    connection_info.ip, connection_info.port
-                ))
-                .await?;
-                stream
-            } else {
-                return Err(err.into());
-            }
-        }
-    };
-    info!("Connected");
-    Ok(stream)
-}
\ No newline at end of file
@@ -0,0 +1,509 @@
+//! Opt-in local API for stream overlays and tracker scripts, so that they can
+//! follow the rooms without a slot connection of their own.
+//!
+//! - `GET /state` answers the sessions and their room as JSON.
+//! - `GET /metrics` answers the [`metrics`](crate::metrics) of every session.
+//! - `GET /events` is a websocket pushing every alert and `PrintJSON`
+//!   message as an [`Event`], one JSON text message each.
+//! - `GET /overlay/` is a page to add as an OBS browser source, see
+//!   [`overlay`].
+//!
+//! Player, item and location names are resolved like in the log.
+//!
+//! Only requests for `localhost` or `127.0.0.1` are answered, so that web
+//! pages cannot reach the API through DNS rebinding, and the websocket only
+//! accepts the API's own pages, such as the overlay, or clients that are not
+//! browsers.
+
+mod overlay;
+
+pub use overlay::FOLDER_NAME as OVERLAY_FOLDER_NAME;
+
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, error, info};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+
+use crate::alert::{Alert, AlertKind};
+use crate::ap::connection::ConnectionId;
+use crate::ap::messages::PrintJSON;
+use crate::metrics;
+use crate::session::Session;
+
+/// Events kept for websocket clients that read slower than they come.
+const EVENT_BUFFER: usize = 256;
+
+/// Longest request head accepted, in bytes.
+const HEAD_LIMIT: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Alert {
+        session: String,
+        kind: AlertKind,
+        text: String,
+    },
+    Print {
+        session: String,
+        /// `type` of the `PrintJSON` message.
+        kind: &'static str,
+        text: String,
+        parts: Vec<Part>,
+    },
+}
+
+/// A `JSONMessagePart` with its name resolved.
+#[derive(Debug, Clone, Serialize)]
+pub struct Part {
+    pub r#type: Option<String>,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player: Option<u32>,
+}
+
+impl Event {
+    /// Whether the event counts against the rate limit of alerts.
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, Event::Alert { kind, .. } if !kind.is_urgent())
+    }
+
+    pub fn alert(alert: &Alert) -> Self {
+        Event::Alert {
+            session: alert.source.clone(),
+            kind: alert.kind,
+            text: alert.text.clone(),
+        }
+    }
+
+    pub fn print(session: &Session, print: &PrintJSON) -> Self {
+        Event::Print {
+            session: session.label().to_owned(),
+            kind: print.kind(),
+            text: session.render(print.data()),
+            parts: print
+                .data()
+                .iter()
+                .map(|part| Part {
+                    r#type: part.r#type.clone(),
+                    text: session.render_part(part),
+                    player: part.player,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SessionState<'a> {
+    label: &'a str,
+    server: &'a str,
+    slot: &'a str,
+    room: Option<RoomState<'a>>,
+    data_storage: &'a BTreeMap<String, Option<Value>>,
+}
+
+#[derive(Serialize)]
+struct RoomState<'a> {
+    team: u32,
+    slot: u32,
+    hint_points: u32,
+    checked_locations: usize,
+    total_locations: usize,
+    players: Vec<PlayerState<'a>>,
+    hints: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct PlayerState<'a> {
+    slot: u32,
+    name: &'a str,
+    alias: &'a str,
+    game: &'a str,
+}
+
+/// Answers of the API about the sessions, built each time they change.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    state: String,
+    metrics: String,
+}
+
+impl Snapshot {
+    fn new(sessions: &BTreeMap<ConnectionId, Session>) -> Self {
+        Self {
+            state: state(sessions),
+            metrics: metrics::render(sessions),
+        }
+    }
+}
+
+/// The sessions and their room, as answered on `GET /state`.
+fn state(sessions: &BTreeMap<ConnectionId, Session>) -> String {
+    let sessions: Vec<SessionState> = sessions
+        .values()
+        .map(|session| SessionState {
+            label: session.label(),
+            server: &session.profile.connection_info.server,
+            slot: &session.profile.connection_info.slot,
+            room: session.room.as_ref().map(|room| RoomState {
+                team: room.team,
+                slot: room.slot,
+                hint_points: room.hint_points,
+                checked_locations: room.checked_locations.len(),
+                total_locations: room.checked_locations.len() + room.missing_locations.len(),
+                players: room
+                    .slot_info
+                    .iter()
+                    .map(|(slot, info)| PlayerState {
+                        slot: *slot,
+                        name: &info.name,
+                        alias: session.player_name(*slot).unwrap_or(&info.name),
+                        game: &info.game,
+                    })
+                    .collect(),
+                hints: session.hints.iter().map(|hint| session.hint_text(hint)).collect(),
+            }),
+            data_storage: &session.data_storage,
+        })
+        .collect();
+    serde_json::json!({ "sessions": sessions }).to_string()
+}
+
+/// Where the API can be reached, for the settings screens.
+pub fn describe(api: Option<&Api>) -> String {
+    match api {
+        Some(api) => format!("http://{0}, browser source at http://{0}/overlay/", api.address),
+        None => String::from("Off, start with --api-port to enable it"),
+    }
+}
+
+/// Handle on the server, which runs on its own thread until the app exits.
+#[derive(Debug)]
+pub struct Api {
+    pub address: SocketAddr,
+    snapshot: watch::Sender<Snapshot>,
+    events: broadcast::Sender<String>,
+}
+
+impl Api {
+    /// Listens on `port` of the loopback interface only, serving the
+    /// customized overlay files of `overlay_dir` if any.
+    pub fn start(port: u16, overlay_dir: Option<PathBuf>) -> anyhow::Result<Self> {
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .with_context(|| format!("could not listen on port {} for the local API", port))?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let (snapshot, snapshots) = watch::channel(Snapshot::new(&BTreeMap::new()));
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let clients = events.clone();
+        std::thread::Builder::new()
+            .name(String::from("api"))
+            .spawn(move || {
+                let result = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|runtime| runtime.block_on(serve(listener, overlay_dir, snapshots, clients)));
+                if let Err(err) = result {
+                    error!("Local API stopped: {:#}", err);
+                }
+            })?;
+
+        info!("Local API listening on http://{}", address);
+        Ok(Self {
+            address,
+            snapshot,
+            events,
+        })
+    }
+
+    pub fn publish(&self, event: &Event) {
+        match serde_json::to_string(event) {
+            // Fails only while no client is connected.
+            Ok(event) => _ = self.events.send(event),
+            Err(err) => error!("Could not serialize {:?}: {}", event, err),
+        }
+    }
+
+    /// Answers the state of `sessions` from now on.
+    pub fn update(&self, sessions: &BTreeMap<ConnectionId, Session>) {
+        self.snapshot.send_replace(Snapshot::new(sessions));
+    }
+}
+
+/// `overlay_dir` holds the customized overlay files, if any.
+async fn serve(
+    listener: std::net::TcpListener,
+    overlay_dir: Option<PathBuf>,
+    snapshot: watch::Receiver<Snapshot>,
+    events: broadcast::Sender<String>,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::from_std(listener)?;
+    let port = listener.local_addr()?.port();
+    let overlay_dir = Arc::new(overlay_dir);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let overlay_dir = overlay_dir.clone();
+        let snapshot = snapshot.clone();
+        let events = events.subscribe();
+        tokio::spawn(async move {
+            if let Err(err) = handle(stream, port, overlay_dir.as_deref(), snapshot, events).await {
+                debug!("Local API client {}: {:#}", peer, err);
+            }
+        });
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+    method: String,
+    /// Without the query string.
+    path: String,
+    /// Names in lowercase.
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    fn parse(head: &str) -> anyhow::Result<Self> {
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+            bail!("malformed request line");
+        };
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_owned()))
+            .collect();
+        Ok(Self {
+            method: method.to_owned(),
+            path: target.split('?').next().unwrap_or_default().to_owned(),
+            headers,
+        })
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_websocket_upgrade(&self) -> bool {
+        self.header("upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+    }
+
+    /// Whether the request is for the loopback address, whatever the port.
+    fn is_for_localhost(&self) -> bool {
+        self.header("host").is_some_and(|host| {
+            let name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+            name.eq_ignore_ascii_case("localhost") || name == "127.0.0.1"
+        })
+    }
+
+    /// Whether the request comes from a page served by the API on `port`, or
+    /// from outside a browser.
+    fn is_from_own_origin(&self, port: u16) -> bool {
+        self.header("origin").map_or(true, |origin| {
+            ["localhost", "127.0.0.1"]
+                .iter()
+                .any(|host| origin.eq_ignore_ascii_case(&format!("http://{}:{}", host, port)))
+        })
+    }
+}
+
+/// Reads the request line and headers, up to the empty line ending them.
+async fn read_head(reader: &mut BufReader<TcpStream>) -> anyhow::Result<String> {
+    let mut head = String::new();
+    loop {
+        let read = reader.read_line(&mut head).await?;
+        if read == 0 {
+            bail!("connection closed before the end of the request");
+        }
+        if head.len() > HEAD_LIMIT {
+            bail!("request head longer than {} bytes", HEAD_LIMIT);
+        }
+        if head.ends_with("\r\n\r\n") || head.ends_with("\n\n") {
+            return Ok(head);
+        }
+    }
+}
+
+/// Answers a request made to the API listening on `port`.
+async fn handle(
+    stream: TcpStream,
+    port: u16,
+    overlay_dir: Option<&Path>,
+    snapshot: watch::Receiver<Snapshot>,
+    events: broadcast::Receiver<String>,
+) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let request = Request::parse(&read_head(&mut reader).await?)?;
+    let mut stream = reader.into_inner();
+
+    if !request.is_for_localhost() {
+        return respond(&mut stream, "403 Forbidden", "text/plain", b"Only localhost is served").await;
+    }
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/state") => {
+            let state = snapshot.borrow().state.clone();
+            respond(&mut stream, "200 OK", "application/json", state.as_bytes()).await
+        }
+        ("GET", "/metrics") => {
+            let metrics = snapshot.borrow().metrics.clone();
+            respond(&mut stream, "200 OK", "text/plain; version=0.0.4", metrics.as_bytes()).await
+        }
+        // Relative links of the page need the trailing slash.
+        ("GET", "/overlay") => {
+            let response = "HTTP/1.1 301 Moved Permanently\r\nLocation: /overlay/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+            stream.write_all(response.as_bytes()).await?;
+            Ok(stream.shutdown().await?)
+        }
+        ("GET", path) if path.starts_with("/overlay/") => {
+            match overlay::file(overlay_dir, &path["/overlay/".len()..]).await {
+                Some((content, content_type)) => respond(&mut stream, "200 OK", content_type, &content).await,
+                None => respond(&mut stream, "404 Not Found", "text/plain", b"Not found").await,
+            }
+        }
+        ("GET", "/events") => match request.header("sec-websocket-key") {
+            Some(_) if !request.is_from_own_origin(port) => {
+                respond(&mut stream, "403 Forbidden", "text/plain", b"Origin not allowed").await
+            }
+            Some(key) if request.is_websocket_upgrade() => {
+                let accept = derive_accept_key(key.as_bytes());
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                            accept
+                        )
+                        .as_bytes(),
+                    )
+                    .await?;
+                push_events(WebSocketStream::from_raw_socket(stream, Role::Server, None).await, events).await
+            }
+            _ => respond(&mut stream, "400 Bad Request", "text/plain", b"Expected a websocket upgrade").await,
+        },
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"Not found").await,
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> anyhow::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len(),
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    Ok(stream.shutdown().await?)
+}
+
+async fn push_events(
+    mut socket: WebSocketStream<TcpStream>,
+    mut events: broadcast::Receiver<String>,
+) -> anyhow::Result<()> {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => socket.send(tungstenite::Message::Text(event)).await?,
+                Err(RecvError::Lagged(skipped)) => debug!("Local API client lagging, {} events skipped", skipped),
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            // Pings are answered while reading.
+            message = socket.next() => match message {
+                Some(Ok(tungstenite::Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err.into()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+
+    #[test]
+    fn parses_request_heads() {
+        let request = Request::parse("GET /events?token=1 HTTP/1.1\r\nUpgrade: WebSocket\r\nSec-WebSocket-Key: abc\r\n\r\n")
+            .unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/events"));
+        assert_eq!(request.header("sec-websocket-key"), Some("abc"));
+        assert!(request.is_websocket_upgrade());
+        assert!(Request::parse("\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn checks_host_and_origin() {
+        let request = |headers: &str| Request::parse(&format!("GET /events HTTP/1.1\r\n{}\r\n", headers)).unwrap();
+        assert!(request("Host: localhost:8080\r\n").is_for_localhost());
+        assert!(request("Host: 127.0.0.1\r\n").is_for_localhost());
+        assert!(!request("Host: attacker.example:8080\r\n").is_for_localhost());
+        assert!(!request("").is_for_localhost());
+
+        assert!(request("").is_from_own_origin(8080));
+        assert!(request("Origin: http://127.0.0.1:8080\r\n").is_from_own_origin(8080));
+        assert!(!request("Origin: http://127.0.0.1:9090\r\n").is_from_own_origin(8080));
+        assert!(!request("Origin: https://attacker.example\r\n").is_from_own_origin(8080));
+    }
+
+    #[test]
+    fn serves_state_and_events() {
+        let overlay_dir = std::env::temp_dir().join(format!("apalert-api-overlay-{}", std::process::id()));
+        std::fs::create_dir_all(&overlay_dir).unwrap();
+        std::fs::write(overlay_dir.join("index.html"), "<p>custom overlay</p>").unwrap();
+        let api = Api::start(0, Some(overlay_dir.clone())).unwrap();
+        api.update(&BTreeMap::new());
+
+        let mut stream = std::net::TcpStream::connect(api.address).unwrap();
+        stream.write_all(b"GET /state HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"sessions":[]}"#));
+        assert!(!response.contains("Access-Control-Allow-Origin"));
+
+        let mut stream = std::net::TcpStream::connect(api.address).unwrap();
+        stream.write_all(b"GET /state HTTP/1.1\r\nHost: rebound.example\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+
+        let mut stream = std::net::TcpStream::connect(api.address).unwrap();
+        stream.write_all(b"GET /overlay/ HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.contains("Content-Type: text/html"));
+        assert!(response.ends_with("<p>custom overlay</p>"));
+        std::fs::remove_dir_all(overlay_dir).unwrap();
+
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}/events", api.address)).unwrap();
+        api.publish(&Event::Alert {
+            session: String::from("Alice"),
+            kind: AlertKind::Goal,
+            text: String::from("Alice has completed their goal."),
+        });
+        let message = socket.read().unwrap();
+        let event: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(event["type"], "alert");
+        assert_eq!(event["session"], "Alice");
+    }
+}
@@ -0,0 +1,78 @@
+//! Browser source page for OBS, served under `/overlay/` and fed by the
+//! `/events` websocket.
+//!
+//! Files in the `overlay` folder of the config folder take the place of the
+//! built-in ones with the same name, and are served next to them, so that
+//! templates, styles, images or sounds can be changed without a rebuild.
+
+use std::path::{Component, Path};
+
+/// Folder of the config folder holding customized files.
+pub const FOLDER_NAME: &str = "overlay";
+
+const BUILT_IN: [(&str, &str); 3] = [
+    ("index.html", include_str!("overlay/index.html")),
+    ("overlay.css", include_str!("overlay/overlay.css")),
+    ("overlay.js", include_str!("overlay/overlay.js")),
+];
+
+/// Content of the file at `path` below `/overlay/`, with its content type.
+pub async fn file(dir: Option<&Path>, path: &str) -> Option<(Vec<u8>, &'static str)> {
+    let name = if path.is_empty() { "index.html" } else { path };
+    let relative = Path::new(name);
+    if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+        return None;
+    }
+
+    let custom = match dir {
+        Some(dir) => tokio::fs::read(dir.join(relative)).await.ok(),
+        None => None,
+    };
+    let content = custom.or_else(|| {
+        BUILT_IN
+            .iter()
+            .find(|(built_in, _)| *built_in == name)
+            .map(|(_, content)| content.as_bytes().to_vec())
+    })?;
+    Some((content, content_type(relative)))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn custom_files_replace_built_in_ones() {
+        let dir = std::env::temp_dir().join(format!("apalert-overlay-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("overlay.css"), "body {}").unwrap();
+
+        let (css, content_type) = file(Some(&dir), "overlay.css").await.unwrap();
+        assert_eq!((css.as_slice(), content_type), (&b"body {}"[..], "text/css; charset=utf-8"));
+        let (index, _) = file(Some(&dir), "").await.unwrap();
+        assert!(String::from_utf8(index).unwrap().contains("<template id=\"goal\">"));
+        assert!(file(Some(&dir), "../config.json").await.is_none());
+        assert!(file(Some(&dir), "missing.png").await.is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
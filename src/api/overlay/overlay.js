@@ -0,0 +1,77 @@
+// Shows the events of the local API with the templates of index.html.
+
+const settings = document.body.dataset;
+const toastSeconds = Number(settings.toastSeconds || 8);
+const hintCount = Number(settings.hintCount || 5);
+
+// A copy of the template with this id filled with the event, null when
+// there is no such template.
+function render(id, event) {
+  const template = document.getElementById(id);
+  if (!template) {
+    return null;
+  }
+  const node = template.content.firstElementChild.cloneNode(true);
+  const fields = [node, ...node.querySelectorAll("[data-field]")];
+  for (const field of fields.filter((field) => field.dataset.field)) {
+    field.textContent = event[field.dataset.field] ?? "";
+  }
+  return node;
+}
+
+function removeLater(node, seconds) {
+  setTimeout(() => {
+    node.classList.add("leaving");
+    setTimeout(() => node.remove(), 500);
+  }, seconds * 1000);
+}
+
+function toast(event) {
+  const node = render(`toast-${event.kind}`, event);
+  if (node) {
+    document.getElementById("toasts").append(node);
+    removeLater(node, toastSeconds);
+  }
+}
+
+function hint(event) {
+  const node = render("hint", event);
+  if (node) {
+    const hints = document.getElementById("hints");
+    hints.prepend(node);
+    while (hints.children.length > hintCount) {
+      hints.lastElementChild.remove();
+    }
+  }
+}
+
+function goal(event) {
+  const node = render("goal", event);
+  if (node) {
+    document.getElementById("celebration").replaceChildren(node);
+    removeLater(node, toastSeconds * 2);
+  }
+}
+
+function handle(event) {
+  if (event.type === "alert") {
+    toast(event);
+  } else if (event.type === "print" && event.kind === "Hint") {
+    hint(event);
+  } else if (event.type === "print" && event.kind === "Goal") {
+    goal(event);
+  }
+}
+
+// Reconnects with a growing delay, so that the overlay survives restarts of
+// AP_Alert.
+function connect(delay) {
+  const socket = new WebSocket(`ws://${location.host}/events`);
+  socket.onopen = () => {
+    delay = 1000;
+  };
+  socket.onmessage = (message) => handle(JSON.parse(message.data));
+  socket.onclose = () => setTimeout(() => connect(Math.min(delay * 2, 30000)), delay);
+}
+
+connect(1000);
@@ -0,0 +1,222 @@
+//! Look of the GUI, saved in the config.
+
+use std::ops::RangeInclusive;
+
+use iced::theme::Palette;
+use iced::{Color, Theme};
+use serde::{Deserialize, Serialize};
+
+/// Theme name picking the custom palette instead of a built-in theme.
+pub const CUSTOM_THEME: &str = "Custom";
+
+pub const TEXT_SCALES: RangeInclusive<f64> = 0.75..=2.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Appearance {
+    /// Name of a built-in iced theme, or [`CUSTOM_THEME`].
+    pub theme: String,
+    pub palette: CustomPalette,
+    /// Scales the whole window, text included.
+    pub text_scale: f64,
+    pub density: Density,
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            theme: Theme::Dracula.to_string(),
+            palette: CustomPalette::from(Palette::DRACULA),
+            text_scale: 1.0,
+            density: Density::default(),
+        }
+    }
+}
+
+impl Appearance {
+    /// Unknown theme names fall back to the default theme.
+    pub fn theme(&self) -> Theme {
+        if self.theme == CUSTOM_THEME {
+            return Theme::custom(CUSTOM_THEME.to_owned(), self.palette.palette());
+        }
+        Theme::ALL
+            .iter()
+            .find(|theme| theme.to_string() == self.theme)
+            .cloned()
+            .unwrap_or(Theme::Dracula)
+    }
+
+    /// Names of the built-in themes, then [`CUSTOM_THEME`].
+    pub fn theme_names() -> Vec<String> {
+        Theme::ALL
+            .iter()
+            .map(Theme::to_string)
+            .chain(std::iter::once(CUSTOM_THEME.to_owned()))
+            .collect()
+    }
+
+    pub fn text_scale(&self) -> f64 {
+        self.text_scale.clamp(*TEXT_SCALES.start(), *TEXT_SCALES.end())
+    }
+}
+
+/// Space between and around widgets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Density {
+    #[default]
+    Comfortable,
+    Compact,
+}
+
+impl Density {
+    pub const ALL: [Density; 2] = [Density::Comfortable, Density::Compact];
+
+    /// `comfortable` is the space used by the comfortable density.
+    pub fn space(self, comfortable: u16) -> u16 {
+        match self {
+            Density::Comfortable => comfortable,
+            Density::Compact => comfortable / 2,
+        }
+    }
+}
+
+impl std::fmt::Display for Density {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Density::Comfortable => "Comfortable",
+            Density::Compact => "Compact",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteColor {
+    Background,
+    Text,
+    Primary,
+    Success,
+    Danger,
+}
+
+impl PaletteColor {
+    pub const ALL: [PaletteColor; 5] = [
+        PaletteColor::Background,
+        PaletteColor::Text,
+        PaletteColor::Primary,
+        PaletteColor::Success,
+        PaletteColor::Danger,
+    ];
+}
+
+impl std::fmt::Display for PaletteColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PaletteColor::Background => "Background",
+            PaletteColor::Text => "Text",
+            PaletteColor::Primary => "Primary",
+            PaletteColor::Success => "Success",
+            PaletteColor::Danger => "Danger",
+        })
+    }
+}
+
+/// Colors of the custom theme as `#rrggbb`, kept as typed so that they can
+/// be edited by hand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomPalette {
+    pub background: String,
+    pub text: String,
+    pub primary: String,
+    pub success: String,
+    pub danger: String,
+}
+
+impl From<Palette> for CustomPalette {
+    fn from(palette: Palette) -> Self {
+        Self {
+            background: to_hex(palette.background),
+            text: to_hex(palette.text),
+            primary: to_hex(palette.primary),
+            success: to_hex(palette.success),
+            danger: to_hex(palette.danger),
+        }
+    }
+}
+
+impl CustomPalette {
+    pub fn get(&self, color: PaletteColor) -> &str {
+        match color {
+            PaletteColor::Background => &self.background,
+            PaletteColor::Text => &self.text,
+            PaletteColor::Primary => &self.primary,
+            PaletteColor::Success => &self.success,
+            PaletteColor::Danger => &self.danger,
+        }
+    }
+
+    pub fn get_mut(&mut self, color: PaletteColor) -> &mut String {
+        match color {
+            PaletteColor::Background => &mut self.background,
+            PaletteColor::Text => &mut self.text,
+            PaletteColor::Primary => &mut self.primary,
+            PaletteColor::Success => &mut self.success,
+            PaletteColor::Danger => &mut self.danger,
+        }
+    }
+
+    /// Colors that do not parse are taken from the default theme.
+    pub fn palette(&self) -> Palette {
+        let color = |color, default| parse_color(self.get(color)).unwrap_or(default);
+        let default = Palette::DRACULA;
+        Palette {
+            background: color(PaletteColor::Background, default.background),
+            text: color(PaletteColor::Text, default.text),
+            primary: color(PaletteColor::Primary, default.primary),
+            success: color(PaletteColor::Success, default.success),
+            danger: color(PaletteColor::Danger, default.danger),
+        }
+    }
+}
+
+/// Parses `#rrggbb`, the `#` being optional.
+pub fn parse_color(hex: &str) -> Option<Color> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn to_hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_round_trips_through_hex() {
+        let palette = CustomPalette::from(Palette::DRACULA);
+        assert_eq!(palette.palette(), Palette::DRACULA);
+        assert_eq!(parse_color("ff8000"), Some(Color::from_rgb8(0xff, 0x80, 0x00)));
+        assert_eq!(parse_color("#ff80"), None);
+    }
+
+    #[test]
+    fn unknown_themes_fall_back_to_the_default() {
+        let appearance = Appearance {
+            theme: String::from("Not a theme"),
+            ..Default::default()
+        };
+        assert_eq!(appearance.theme(), Theme::Dracula);
+
+        let appearance = Appearance {
+            theme: Theme::Nord.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(appearance.theme(), Theme::Nord);
+    }
+}
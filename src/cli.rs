@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::ap::connection::Speed;
+
+/// Alerts for Archipelago multiworlds.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Run without the GUI, printing alerts on stdout.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Run in the terminal instead of a window.
+    #[arg(long, conflicts_with = "headless")]
+    pub tui: bool,
+
+    /// Open the window as the compact always-on-top alert overlay.
+    #[arg(long, conflicts_with_all = ["headless", "tui"])]
+    pub overlay: bool,
+
+    /// Name of a saved profile to connect with, can be repeated. Without it
+    /// the headless mode uses the most recently used profile.
+    #[arg(long = "profile", value_name = "NAME")]
+    pub profiles: Vec<String>,
+
+    /// Server address to connect to instead of a saved profile.
+    #[arg(long, requires = "slot")]
+    pub server: Option<String>,
+
+    /// Slot name to connect with, used together with --server.
+    #[arg(long, requires = "server")]
+    pub slot: Option<String>,
+
+    /// Slot password, for profiles that do not have one saved.
+    #[arg(long, env = "AP_ALERT_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+
+    /// Passphrase unlocking the saved passwords.
+    #[arg(long, env = "AP_ALERT_PASSPHRASE", hide_env_values = true)]
+    pub passphrase: Option<String>,
+
+    /// Serve the room state, live events and Prometheus metrics on this port
+    /// of localhost, for stream overlays, tracker scripts and dashboards.
+    #[arg(long, value_name = "PORT", env = "AP_ALERT_API_PORT")]
+    pub api_port: Option<u16>,
+
+    /// Record the frames received by every session to a file in this folder.
+    #[arg(long, value_name = "DIR")]
+    pub record: Option<PathBuf>,
+
+    /// Play a recorded session instead of connecting.
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Playback speed of --replay, 2 being twice as fast as recorded.
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0, requires = "replay")]
+    pub replay_speed: f64,
+
+    /// Play --replay one frame at a time, with the "Next frame" button, Ctrl-N
+    /// in the terminal UI or Enter in headless mode.
+    #[arg(long, requires = "replay")]
+    pub step: bool,
+}
+
+impl Cli {
+    pub fn replay_speed(&self) -> Speed {
+        if self.step {
+            Speed::Stepped
+        } else {
+            Speed::Scaled(self.replay_speed)
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::appearance::Appearance;
+use crate::overlay::Overlay;
+use crate::profile::Profile;
+use crate::secrets::PasswordStorage;
+use crate::sink;
+use crate::throttle;
+use crate::window_state::WindowState;
+
+const QUALIFIER: &str = "pw";
+const ORGANIZATION: &str = "olympus_inc";
+const APPLICATION: &str = "APAlert";
+const CONFIG_FILE_NAME: &str = "config.json";
+
+/// Version written by this build, bump it together with a new entry in
+/// [`MIGRATIONS`].
+pub const CONFIG_VERSION: u64 = 2;
+
+/// `MIGRATIONS[n]` turns a version `n` config into a version `n + 1` one.
+const MIGRATIONS: [fn(Value) -> anyhow::Result<Value>; CONFIG_VERSION as usize] = [
+    migrate_v0_single_connection,
+    migrate_v1_add_version,
+];
+
+/// What is saved in `config.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Config {
+    pub version: u64,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub password_storage: PasswordStorage,
+    #[serde(default)]
+    pub appearance: Appearance,
+    #[serde(default)]
+    pub window: WindowState,
+    #[serde(default)]
+    pub overlay: Overlay,
+    #[serde(default)]
+    pub sinks: sink::Settings,
+    #[serde(default)]
+    pub throttle: throttle::Settings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            profiles: Vec::new(),
+            password_storage: PasswordStorage::default(),
+            appearance: Appearance::default(),
+            window: WindowState::default(),
+            overlay: Overlay::default(),
+            sinks: sink::Settings::default(),
+            throttle: throttle::Settings::default(),
+        }
+    }
+}
+
+pub fn config_dir() -> anyhow::Result<PathBuf> {
+    let path = directories::ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
+        .ok_or_else(|| anyhow!("could not find a home directory"))?;
+
+    std::fs::create_dir_all(path.config_dir())
+        .with_context(|| format!("could not create {}", path.config_dir().display()))?;
+    Ok(path.config_dir().to_owned())
+}
+
+pub fn config_path() -> anyhow::Result<PathBuf> {
+    Ok(config_dir()?.join(CONFIG_FILE_NAME))
+}
+
+/// Reads and migrates the config, `None` if there is no config yet.
+pub fn load(path: &Path) -> anyhow::Result<Option<Config>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("could not read {}", path.display())),
+    };
+
+    let value = serde_json::from_str(&content).context("invalid JSON")?;
+    let config = serde_json::from_value(migrate(value)?).context("invalid config")?;
+    Ok(Some(config))
+}
+
+pub fn migrate(mut value: Value) -> anyhow::Result<Value> {
+    let mut version = match value.get("version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| anyhow!("invalid config version {}", version))?,
+        None if value.get("profiles").is_some() => 1,
+        None => 0,
+    };
+    if version > CONFIG_VERSION {
+        bail!(
+            "config version {} is newer than the supported version {}",
+            version,
+            CONFIG_VERSION
+        );
+    }
+
+    while version < CONFIG_VERSION {
+        value = MIGRATIONS[version as usize](value)
+            .with_context(|| format!("could not migrate config from version {}", version))?;
+        version += 1;
+    }
+    Ok(value)
+}
+
+/// Copies a config that could not be loaded next to it, before it gets
+/// overwritten by the next save.
+pub fn backup(path: &Path) -> anyhow::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let backup = path.with_extension(format!("{}.bak", timestamp));
+
+    std::fs::copy(path, &backup)
+        .with_context(|| format!("could not back up {}", path.display()))?;
+    Ok(backup)
+}
+
+pub fn save(path: &Path, config: &Config) -> anyhow::Result<()> {
+    write_atomic(path, &serde_json::to_vec_pretty(config)?)
+}
+
+/// Writes to a temporary file and renames it over `path`, so that a crash
+/// never leaves a half written file behind.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let temporary = path.with_extension("tmp");
+
+    let mut file = std::fs::File::create(&temporary)
+        .with_context(|| format!("could not create {}", temporary.display()))?;
+    file.write_all(contents)
+        .and_then(|_| file.sync_all())
+        .with_context(|| format!("could not write {}", temporary.display()))?;
+    drop(file);
+
+    std::fs::rename(&temporary, path)
+        .with_context(|| format!("could not replace {}", path.display()))
+}
+
+// Version 0 held a single `connection_info`, which first split the server
+// address into `ip` and `port`.
+fn migrate_v0_single_connection(mut value: Value) -> anyhow::Result<Value> {
+    let mut connection_info = value
+        .get_mut("connection_info")
+        .map(Value::take)
+        .unwrap_or_else(|| json!({}));
+    let fields = connection_info
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("connection_info is not an object"))?;
+
+    if !fields.contains_key("server") {
+        if let Some(ip) = fields.remove("ip").and_then(|ip| ip.as_str().map(str::to_owned)) {
+            let ip = match ip.parse::<std::net::Ipv6Addr>() {
+                Ok(_) => format!("[{}]", ip),
+                Err(_) => ip,
+            };
+            let server = match fields.remove("port").as_ref().and_then(Value::as_str) {
+                Some(port) if !port.is_empty() => format!("{}:{}", ip, port),
+                _ => ip,
+            };
+            fields.insert("server".to_owned(), Value::String(server));
+        }
+    }
+
+    Ok(json!({
+        "profiles": [{
+            "name": "Default",
+            "connection_info": connection_info,
+        }],
+    }))
+}
+
+fn migrate_v1_add_version(mut value: Value) -> anyhow::Result<Value> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("config is not an object"))?
+        .insert("version".to_owned(), json!(2));
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_v0_ip_and_port_to_a_server_address() {
+        let v0 = json!({
+            "connection_info": { "ip": "::1", "port": "38281", "slot": "Alice", "password": "" },
+        });
+        let config: Config = serde_json::from_value(migrate(v0).unwrap()).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        let connection_info = &config.profiles[0].connection_info;
+        assert_eq!(connection_info.server, "[::1]:38281");
+        assert_eq!(connection_info.slot, "Alice");
+
+        let v0 = json!({ "connection_info": { "ip": "archipelago.gg", "port": "" } });
+        let value = migrate_v0_single_connection(v0).unwrap();
+        assert_eq!(value["profiles"][0]["connection_info"]["server"], "archipelago.gg");
+    }
+}
@@ -0,0 +1,649 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context as _;
+use tracing::error;
+
+use crate::alert::Alert;
+use crate::api::{self, Api};
+use crate::appearance::Appearance;
+use crate::ap::connection::{self, ConnectionId, InputMessage, Source, Speed};
+use crate::ap::messages::APServerMessage;
+use crate::cli::Cli;
+use crate::inbox::{self, Inbox, SourceMessage};
+use crate::config::{self, Config};
+use crate::overlay::Overlay;
+use crate::profile::{unique_name, Profile, ProfileChoice};
+use crate::secrets::{new_secret_id, PasswordStorage, SecretStore};
+use crate::session::Session;
+use crate::sink::{self, Sinks};
+use crate::throttle::{self, RateLimit, Throttle};
+use crate::window_state::WindowState;
+
+/// State shared by every front end: saved profiles, running sessions and
+/// their alerts.
+#[derive(Debug)]
+pub struct Context {
+    /// Sorted by last use, most recent first.
+    pub profiles: Vec<Profile>,
+    pub password_storage: PasswordStorage,
+    pub secrets: SecretStore,
+    /// Set while the loaded config still holds passwords in clear text. They
+    /// keep being written there until they can be moved to the secret store.
+    pub plaintext_passwords: bool,
+    pub selected_profile: usize,
+    pub sessions: BTreeMap<ConnectionId, Session>,
+    next_connection_id: u64,
+    /// Latest alerts of every session, oldest first. Older ones are only
+    /// kept in the inboxes.
+    pub alerts: VecDeque<(ConnectionId, Alert)>,
+    /// Shown on top of every page until dismissed.
+    pub errors: Vec<String>,
+    /// Folder new live sessions are recorded to, if any.
+    pub record_dir: Option<PathBuf>,
+    /// Folder the inboxes are saved to, unset when the config folder is
+    /// unknown.
+    pub inbox_dir: Option<PathBuf>,
+    /// Folder of the customized overlay files of the local API, unset when
+    /// the config folder is unknown.
+    pub overlay_dir: Option<PathBuf>,
+    pub appearance: Appearance,
+    pub window: WindowState,
+    pub overlay: Overlay,
+    /// Local API started with `--api-port`, if any.
+    pub api: Option<Api>,
+    pub sink_settings: sink::Settings,
+    /// Started from [`Context::sink_settings`] by [`Context::apply_cli`].
+    pub sinks: Sinks,
+    pub throttle_settings: throttle::Settings,
+    /// What alerts go through before being raised.
+    pub throttle: Throttle,
+    /// Alerts pushed to the local API over the last minute.
+    api_limit: RateLimit,
+    /// Events for the local API and the sinks, handed to them by
+    /// [`Context::publish`].
+    events: Vec<(ConnectionId, api::Event)>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            profiles: vec![Profile::default()],
+            password_storage: PasswordStorage::default(),
+            secrets: SecretStore::default(),
+            plaintext_passwords: false,
+            selected_profile: 0,
+            sessions: BTreeMap::new(),
+            next_connection_id: 0,
+            alerts: VecDeque::new(),
+            errors: Vec::new(),
+            record_dir: None,
+            inbox_dir: None,
+            overlay_dir: None,
+            appearance: Appearance::default(),
+            window: WindowState::default(),
+            overlay: Overlay::default(),
+            api: None,
+            sink_settings: sink::Settings::default(),
+            sinks: Sinks::default(),
+            throttle_settings: throttle::Settings::default(),
+            throttle: Throttle::default(),
+            api_limit: RateLimit::default(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl From<Config> for Context {
+    fn from(config: Config) -> Self {
+        let mut context = Self {
+            plaintext_passwords: config
+                .profiles
+                .iter()
+                .any(|profile| !profile.connection_info.password.is_empty())
+                || config.sinks.has_passwords(),
+            password_storage: config.password_storage,
+            appearance: config.appearance,
+            window: config.window,
+            overlay: config.overlay,
+            sink_settings: config.sinks,
+            throttle_settings: config.throttle,
+            ..Default::default()
+        };
+        if !config.profiles.is_empty() {
+            context.profiles = config.profiles;
+        }
+        context.sort_profiles();
+        context.select_profile(0);
+        context
+    }
+}
+
+const SECRETS_FILE_NAME: &str = "secrets.json";
+
+/// Alerts kept in [`Context::alerts`], older ones are dropped.
+const ALERT_LIMIT: usize = 1000;
+
+impl Context {
+    pub fn try_load_from_save() -> Self {
+        let loaded = config::config_path().and_then(|path| match config::load(&path) {
+            Ok(config) => Ok(config),
+            Err(err) => match config::backup(&path) {
+                Ok(backup) => Err(err.context(format!(
+                    "Could not load the config, using defaults. The old config was backed up to {}",
+                    backup.display()
+                ))),
+                Err(backup_err) => Err(err.context(format!(
+                    "Could not load the config, using defaults. Backing it up failed too: {:#}",
+                    backup_err
+                ))),
+            },
+        });
+
+        let mut context = Self::default();
+        match loaded {
+            Ok(Some(config)) => context = Self::from(config),
+            Ok(None) => {}
+            Err(err) => context.report(err),
+        }
+        match config::config_dir() {
+            Ok(dir) => {
+                context.secrets = SecretStore::new(dir.join(SECRETS_FILE_NAME));
+                context.inbox_dir = Some(dir.join(inbox::FOLDER_NAME));
+                context.overlay_dir = Some(dir.join(api::OVERLAY_FOLDER_NAME));
+            }
+            Err(err) => context.report(err),
+        }
+        context
+    }
+
+    /// Logs an error and shows it on top of the current page.
+    pub fn report(&mut self, err: anyhow::Error) {
+        error!("{:#}", err);
+        self.errors.push(format!("{:#}", err));
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profiles[self.selected_profile]
+    }
+
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        &mut self.profiles[self.selected_profile]
+    }
+
+    pub fn profile_choices(&self) -> Vec<ProfileChoice> {
+        self.profiles
+            .iter()
+            .enumerate()
+            .map(|(index, profile)| ProfileChoice {
+                index,
+                name: profile.name.clone(),
+            })
+            .collect()
+    }
+
+    pub fn select_profile(&mut self, index: usize) {
+        self.selected_profile = index.min(self.profiles.len() - 1);
+    }
+
+    pub fn new_profile(&mut self) {
+        let name = unique_name(&self.profiles, "New profile");
+        self.profiles.push(Profile {
+            name,
+            ..Default::default()
+        });
+        self.select_profile(self.profiles.len() - 1);
+    }
+
+    pub fn duplicate_profile(&mut self) {
+        let mut profile = self.profile().clone();
+        profile.id = new_secret_id();
+        profile.name = unique_name(&self.profiles, &format!("{} (copy)", profile.name));
+        profile.last_used = None;
+        self.profiles.push(profile);
+        self.select_profile(self.profiles.len() - 1);
+    }
+
+    pub fn delete_profile(&mut self) {
+        self.profiles.remove(self.selected_profile);
+        if self.profiles.is_empty() {
+            self.profiles.push(Profile::default());
+        }
+        self.select_profile(self.selected_profile);
+    }
+
+    /// Marks the selected profile as used and moves it to the front.
+    pub fn touch_profile(&mut self) {
+        self.profile_mut().touch();
+        self.sort_profiles();
+    }
+
+    /// Mutes or unmutes `slot` in session `id` and in the profile it was
+    /// started from. Returns whether the profile changed.
+    pub fn mute_player(&mut self, id: ConnectionId, slot: u32, muted: bool) -> bool {
+        let Some(session) = self.sessions.get_mut(&id) else {
+            return false;
+        };
+        let names: Vec<String> = session
+            .room
+            .as_ref()
+            .and_then(|room| room.slot_info.get(&slot))
+            .map(|info| info.name.clone())
+            .into_iter()
+            .chain(session.player_name(slot).map(str::to_owned))
+            .collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        session.mute(&names, muted);
+        let profile = self.profiles.iter_mut().find(|profile| profile.id == session.profile.id);
+        match profile {
+            Some(profile) => {
+                profile.mentions.set_muted(&names, muted);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the watchlist of the selected profile to the session running
+    /// it, if any.
+    pub fn apply_watchlist(&mut self) {
+        let profile = &self.profiles[self.selected_profile];
+        let running = self
+            .sessions
+            .values_mut()
+            .filter(|session| !session.is_replay() && session.profile.id == profile.id);
+        for session in running {
+            session.set_watchlist(profile.watchlist.clone());
+        }
+    }
+
+    /// Starts a session for the selected profile, or reconnects the session
+    /// already running it with the updated settings.
+    pub fn start_session(&mut self) -> ConnectionId {
+        self.start_session_for(self.profile().clone())
+    }
+
+    pub fn start_session_for(&mut self, profile: Profile) -> ConnectionId {
+        let running = self
+            .sessions
+            .iter_mut()
+            .find(|(_, session)| !session.is_replay() && session.profile.id == profile.id);
+        if let Some((id, session)) = running {
+            session.set_profile(profile);
+            session.connect();
+            return *id;
+        }
+
+        let record = self.record_dir.as_ref().map(|dir| {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default();
+            let name: String = profile
+                .name
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            dir.join(format!("{}-{}.jsonl", name, timestamp))
+        });
+        self.insert_session(Session::new(profile, Source::Live { record }))
+    }
+
+    /// Starts a session playing back a recording.
+    pub fn start_replay(&mut self, path: &Path, speed: Speed) -> anyhow::Result<ConnectionId> {
+        let frames = ap_client::recording::load(path)
+            .with_context(|| format!("could not load the recording {}", path.display()))?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let profile = Profile {
+            name: format!("Replay of {}", name),
+            ..Default::default()
+        };
+        let source = Source::Replay {
+            frames: Arc::new(frames),
+            speed,
+        };
+        Ok(self.insert_session(Session::new(profile, source)))
+    }
+
+    /// Applies the recording and replay options of the command line.
+    pub fn apply_cli(&mut self, cli: &Cli) -> anyhow::Result<()> {
+        if let Some(dir) = &cli.record {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+            self.record_dir = Some(dir.clone());
+        }
+        if let Some(port) = cli.api_port {
+            self.api = Some(Api::start(port, self.overlay_dir.clone())?);
+        }
+        let limits = &self.throttle_settings.rate_limits;
+        self.api_limit = RateLimit::new(limits.api);
+        self.sinks = Sinks::start(&self.sink_settings, limits);
+        if let Some(path) = &cli.replay {
+            self.start_replay(path, cli.replay_speed())?;
+        }
+        Ok(())
+    }
+
+    fn insert_session(&mut self, session: Session) -> ConnectionId {
+        let id = ConnectionId(self.next_connection_id);
+        self.next_connection_id += 1;
+        self.sessions.insert(id, session);
+        id
+    }
+
+    /// Dropping the session drops its subscription, which stops the worker.
+    pub fn stop_session(&mut self, id: ConnectionId) {
+        self.sessions.remove(&id);
+        self.throttle.forget(id);
+    }
+
+    /// Asks the worker of a session to close its socket, the session is
+    /// stopped once it is done.
+    pub fn close_session(&mut self, id: ConnectionId) {
+        let Some(session) = self.sessions.get_mut(&id) else {
+            return;
+        };
+        if !session.close() {
+            self.stop_session(id);
+        }
+    }
+
+    /// Whether a session is running and not closing.
+    pub fn has_sessions(&self) -> bool {
+        self.sessions.values().any(|session| !session.closing)
+    }
+
+    /// Takes the messages queued by every session for its worker.
+    pub fn take_outgoing(&mut self) -> Vec<(ConnectionId, InputMessage)> {
+        self.sessions
+            .iter_mut()
+            .flat_map(|(id, session)| session.outbox.drain(..).map(|message| (*id, message)))
+            .collect()
+    }
+
+    /// Hands a message to the worker of a session. Dropped if the worker is
+    /// not ready yet, it asks for a `Connect` once it is.
+    pub fn deliver(&mut self, id: ConnectionId, message: InputMessage) {
+        let worker = self
+            .sessions
+            .get_mut(&id)
+            .and_then(|session| session.worker_channel.as_mut());
+        if let Some(worker) = worker {
+            worker.send(message);
+        }
+    }
+
+    /// Delivers every queued message, for front ends that do not go through
+    /// [`State`](crate::update::State).
+    pub fn flush(&mut self) {
+        for (id, message) in self.take_outgoing() {
+            self.deliver(id, message);
+        }
+        self.publish();
+        self.save_inboxes();
+    }
+
+    /// Whether events are handed to the local API or a sink.
+    pub fn is_publishing(&self) -> bool {
+        self.api.is_some() || !self.sinks.is_empty()
+    }
+
+    /// Hands the queued events and the current state of the sessions to the
+    /// local API and the sinks.
+    pub fn publish(&mut self) {
+        let now = Instant::now();
+        for (id, event) in self.events.drain(..) {
+            if let Some(api) = &self.api {
+                if !event.is_rate_limited() || self.api_limit.allow(now) {
+                    api.publish(&event);
+                }
+            }
+            if let Some(session) = self.sessions.get(&id) {
+                self.sinks.publish(session, &event, now);
+            }
+        }
+        if let Some(api) = &self.api {
+            api.update(&self.sessions);
+        }
+        for session in self.sessions.values() {
+            self.sinks.update(session);
+        }
+    }
+
+    /// Updates the session the event came from, returning the alerts
+    /// raised, see [`throttle`].
+    pub fn process(&mut self, id: ConnectionId, event: &connection::Event) -> Vec<Alert> {
+        let publishing = self.is_publishing();
+        let Some(session) = self.sessions.get_mut(&id) else {
+            return Vec::new();
+        };
+        if session.closing && matches!(event, connection::Event::Disconnected) {
+            self.stop_session(id);
+            return Vec::new();
+        }
+        let alert = session.process(event);
+        let mut load_error = None;
+        if let (Some(dir), connection::Event::APMessage(APServerMessage::Connected(_))) = (&self.inbox_dir, event) {
+            let path = Inbox::path(dir, session.seed.as_deref(), &session.profile.connection_info.slot);
+            if !session.inbox.is_at(&path) {
+                match Inbox::load(path) {
+                    Ok(inbox) => session.inbox = inbox,
+                    Err(err) => load_error = Some(err),
+                }
+            }
+        }
+        let print = match event {
+            connection::Event::APMessage(APServerMessage::PrintJSON(print)) => Some(print),
+            _ => None,
+        };
+        if let Some(print) = print.filter(|_| publishing) {
+            self.events.push((id, api::Event::print(session, print)));
+        }
+        let alerts = self
+            .throttle
+            .push(&self.throttle_settings, id, session, print, alert, Instant::now());
+        if let Some(err) = load_error {
+            self.report(err);
+        }
+        self.raise(alerts)
+    }
+
+    /// Raises the alerts the throttle held back until `now`.
+    pub fn tick(&mut self, now: Instant) -> Vec<Alert> {
+        let alerts = self.throttle.tick(&self.throttle_settings, now);
+        self.raise(alerts)
+    }
+
+    fn raise(&mut self, alerts: Vec<(ConnectionId, Alert)>) -> Vec<Alert> {
+        if self.is_publishing() {
+            self.events.extend(alerts.iter().map(|(id, alert)| (*id, api::Event::alert(alert))));
+        }
+        let fired = inbox::now();
+        for (id, alert) in &alerts {
+            if let Some(session) = self.sessions.get_mut(id) {
+                let message = alert
+                    .log_index
+                    .and_then(|index| session.log_position(index))
+                    .map(|position| SourceMessage::new(session, &session.log[position]));
+                session.inbox.push(alert, message, fired);
+            }
+        }
+        self.alerts.extend(alerts.iter().cloned());
+        let dropped = self.alerts.len().saturating_sub(ALERT_LIMIT);
+        self.alerts.drain(..dropped);
+        alerts.into_iter().map(|(_, alert)| alert).collect()
+    }
+
+    /// Whether an inbox changed since it was last saved.
+    pub fn inboxes_changed(&self) -> bool {
+        self.sessions.values().any(|session| session.inbox.has_changed())
+    }
+
+    pub fn save_inboxes(&mut self) {
+        let mut errors = Vec::new();
+        for session in self.sessions.values_mut() {
+            if let Err(err) = session.inbox.save() {
+                errors.push(err);
+            }
+        }
+        for err in errors {
+            self.report(err);
+        }
+    }
+
+    fn sort_profiles(&mut self) {
+        let mut profiles: Vec<_> = std::mem::take(&mut self.profiles).into_iter().enumerate().collect();
+        profiles.sort_by_key(|(_, profile)| std::cmp::Reverse(profile.last_used));
+        self.selected_profile = profiles
+            .iter()
+            .position(|(index, _)| *index == self.selected_profile)
+            .unwrap_or_default();
+        self.profiles = profiles.into_iter().map(|(_, profile)| profile).collect();
+    }
+
+    /// Unlocks the secret store, filling in the saved passwords and moving
+    /// any password still in the config into it.
+    pub fn unlock_secrets(&mut self, passphrase: &str) -> anyhow::Result<()> {
+        self.secrets.unlock(passphrase)?;
+        self.fill_in_secrets();
+        self.plaintext_passwords = false;
+        self.save();
+        Ok(())
+    }
+
+    /// Fills in the passwords missing from the config from the unlocked
+    /// secret store.
+    fn fill_in_secrets(&mut self) {
+        for profile in &mut self.profiles {
+            if profile.connection_info.password.is_empty() {
+                if let Some(password) = self.secrets.get(&profile.id) {
+                    profile.connection_info.password = password.to_owned();
+                }
+            }
+        }
+        let mut restart_sinks = false;
+        for (id, password) in self.sink_settings.passwords_mut() {
+            if let (None, Some(secret)) = (&password, self.secrets.get(id)) {
+                password.replace(secret.to_owned());
+                restart_sinks = true;
+            }
+        }
+        // Sinks already running logged in without their password.
+        if restart_sinks && !self.sinks.is_empty() {
+            self.sinks = Sinks::start(&self.sink_settings, &self.throttle_settings.rate_limits);
+        }
+    }
+
+    pub fn save(&mut self) {
+        if self.password_storage == PasswordStorage::Encrypted && self.secrets.is_unlocked() {
+            self.store_secrets();
+            if let Err(err) = self.secrets.save() {
+                self.report(err);
+            }
+        }
+
+        let saved = self.saved_config();
+        if let Err(err) = config::config_path().and_then(|path| config::save(&path, &saved)) {
+            self.report(err.context("Could not save the config"));
+        }
+    }
+
+    /// Puts the passwords of the profiles and sinks in the secret store.
+    fn store_secrets(&mut self) {
+        for profile in &self.profiles {
+            self.secrets.set(&profile.id, &profile.connection_info.password);
+        }
+        for (id, password) in self.sink_settings.passwords() {
+            self.secrets.set(id, password.unwrap_or_default());
+        }
+        let profiles = &self.profiles;
+        self.secrets
+            .retain(|id| sink::SECRET_IDS.contains(&id) || profiles.iter().any(|profile| profile.id == id));
+    }
+
+    /// The config to write, without the passwords once they are kept in the
+    /// secret store or not kept at all.
+    fn saved_config(&self) -> Config {
+        let mut saved = Config {
+            profiles: self.profiles.clone(),
+            password_storage: self.password_storage,
+            appearance: self.appearance.clone(),
+            window: self.window.clone(),
+            overlay: self.overlay.clone(),
+            sinks: self.sink_settings.clone(),
+            throttle: self.throttle_settings.clone(),
+            ..Default::default()
+        };
+        if !self.plaintext_passwords {
+            for profile in &mut saved.profiles {
+                profile.connection_info.password.clear();
+            }
+            for (_, password) in saved.sinks.passwords_mut() {
+                password.take();
+            }
+        }
+        saved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::AlertKind;
+    use crate::sink::mqtt;
+
+    #[test]
+    fn keeps_the_latest_alerts() {
+        let mut context = Context::default();
+        let alert = |text: String| Alert {
+            kind: AlertKind::Goal,
+            source: String::from("Room"),
+            text,
+            at: Instant::now(),
+            log_index: None,
+        };
+        for batch in 0..3 {
+            let alerts = (0..ALERT_LIMIT / 2).map(|index| (ConnectionId(0), alert(format!("{}-{}", batch, index))));
+            context.raise(alerts.collect());
+        }
+        assert_eq!(context.alerts.len(), ALERT_LIMIT);
+        assert_eq!(context.alerts[0].1.text, "1-0");
+    }
+
+    #[test]
+    fn keeps_sink_passwords_in_the_secret_store() {
+        let config = Config {
+            sinks: sink::Settings {
+                mqtt: Some(mqtt::Settings {
+                    password: Some(String::from("broker secret")),
+                    ..Default::default()
+                }),
+                smtp: None,
+            },
+            ..Default::default()
+        };
+        let mut context = Context::from(config);
+        assert!(context.plaintext_passwords);
+        assert!(context.saved_config().sinks.has_passwords());
+
+        let path = std::env::temp_dir().join(format!("apalert-sink-secrets-{}.json", std::process::id()));
+        context.secrets = SecretStore::new(path.clone());
+        context.secrets.unlock("hunter2").unwrap();
+        context.store_secrets();
+        context.plaintext_passwords = false;
+        let saved = context.saved_config();
+        assert!(!saved.sinks.has_passwords());
+        assert_eq!(context.secrets.get(sink::SECRET_IDS[0]), Some("broker secret"));
+
+        let mut restarted = Context::from(saved);
+        restarted.secrets = std::mem::take(&mut context.secrets);
+        restarted.fill_in_secrets();
+        assert_eq!(restarted.sink_settings.passwords()[0].1, Some("broker secret"));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use futures_util::StreamExt;
+use iced::futures::channel::mpsc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::info;
+
+use crate::alert::Alert;
+use crate::ap::connection::{self, ConnectionId, ConnectionInfo, Event};
+use crate::cli::Cli;
+use crate::context::Context;
+use crate::profile::Profile;
+use crate::throttle;
+
+/// How long workers get to close their socket on shutdown.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs the connection workers and alert rules without the GUI, until
+/// SIGTERM or Ctrl-C.
+pub fn run(cli: Cli) -> anyhow::Result<()> {
+    tokio::runtime::Runtime::new()?.block_on(run_async(cli))
+}
+
+async fn run_async(cli: Cli) -> anyhow::Result<()> {
+    let mut context = Context::try_load_from_save();
+    if let Some(passphrase) = &cli.passphrase {
+        context.unlock_secrets(passphrase)?;
+    }
+
+    context.apply_cli(&cli)?;
+
+    let replay_only = cli.replay.is_some() && cli.profiles.is_empty() && cli.server.is_none();
+    if !replay_only {
+        for profile in selected_profiles(&context, &cli)? {
+            info!("Connecting to {} as {}", profile.connection_info.server, profile.connection_info.slot);
+            context.start_session_for(profile);
+        }
+    }
+
+    let (sender, mut receiver) = mpsc::channel(100);
+    for (id, session) in &context.sessions {
+        tokio::spawn(connection::run(*id, session.source.clone(), sender.clone()));
+    }
+
+    // Stepped replays play a frame per line read.
+    let mut steps = BufReader::new(tokio::io::stdin()).lines();
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    let mut tick = tokio::time::interval(throttle::TICK);
+
+    loop {
+        tokio::select! {
+            Some((id, event)) = receiver.next() => {
+                print_alerts(context.process(id, &event));
+            }
+            now = tick.tick() => print_alerts(context.tick(now.into_std())),
+            Ok(Some(_)) = steps.next_line(), if cli.step => {
+                for session in context.sessions.values_mut() {
+                    session.step();
+                }
+            }
+            result = &mut shutdown => {
+                result?;
+                break;
+            }
+        }
+        context.flush();
+    }
+
+    info!("Shutting down");
+    disconnect_all(&mut context, &mut receiver).await;
+
+    Ok(())
+}
+
+fn print_alerts(alerts: Vec<Alert>) {
+    for alert in alerts {
+        println!("[{}] [{}] {}", alert.source, alert.kind, alert.text);
+    }
+}
+
+/// Asks every worker to close its socket and waits until they are done, or
+/// until [`SHUTDOWN_TIMEOUT`].
+pub async fn disconnect_all(
+    context: &mut Context,
+    receiver: &mut mpsc::Receiver<(ConnectionId, Event)>,
+) {
+    let mut closing = context.sessions.len();
+    for session in context.sessions.values_mut() {
+        session.disconnect();
+    }
+    context.flush();
+    let _ = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+        while closing > 0 {
+            match receiver.next().await {
+                Some((_, Event::Disconnected)) => closing -= 1,
+                Some(_) => {}
+                None => break,
+            }
+        }
+    })
+    .await;
+}
+
+pub fn selected_profiles(context: &Context, cli: &Cli) -> anyhow::Result<Vec<Profile>> {
+    let mut profiles = cli
+        .profiles
+        .iter()
+        .map(|name| {
+            context
+                .profiles
+                .iter()
+                .find(|profile| &profile.name == name)
+                .cloned()
+                .ok_or_else(|| anyhow!("no profile named \"{}\"", name))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if let (Some(server), Some(slot)) = (&cli.server, &cli.slot) {
+        profiles.push(Profile {
+            name: format!("{}@{}", slot, server),
+            connection_info: ConnectionInfo {
+                server: server.clone(),
+                slot: slot.clone(),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    }
+
+    if profiles.is_empty() {
+        let profile = context.profile();
+        if profile.connection_info.slot.is_empty() {
+            bail!("no profile is set up, pass --profile or --server and --slot");
+        }
+        profiles.push(profile.clone());
+    }
+
+    if let Some(password) = &cli.password {
+        for profile in &mut profiles {
+            if profile.connection_info.password.is_empty() {
+                profile.connection_info.password = password.clone();
+            }
+        }
+    }
+
+    Ok(profiles)
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = terminate.recv() => Ok(()),
+        result = tokio::signal::ctrl_c() => Ok(result?),
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> anyhow::Result<()> {
+    Ok(tokio::signal::ctrl_c().await?)
+}
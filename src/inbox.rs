@@ -0,0 +1,269 @@
+//! Every alert fired for a slot, kept so that alerts fired while away are
+//! not lost. Each inbox is saved in the `inbox` folder of the config folder,
+//! one file per seed and slot, and loaded again when the slot connects.
+//!
+//! Logs are not saved, so only alerts fired since the app started can jump
+//! to their message in the log. Each entry keeps a copy of the message it
+//! was raised by, shown in its place for the others.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::alert::{Alert, AlertKind};
+use crate::ap::messages::PrintJSON;
+use crate::config;
+use crate::session::Session;
+use crate::throttle;
+
+/// Folder of the config folder holding the inboxes.
+pub const FOLDER_NAME: &str = "inbox";
+
+/// Alerts kept in each inbox, older ones are dropped.
+const INBOX_LIMIT: usize = 1000;
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Seconds since the Unix epoch of the next time the local clock reads
+/// `minute` minutes since midnight, `now` being the local minute `local`.
+pub fn next_time(now: u64, local: u32, minute: u32) -> u64 {
+    let minutes = match (minute + 24 * 60 - local) % (24 * 60) {
+        0 => 24 * 60,
+        minutes => minutes,
+    };
+    now - now % 60 + minutes as u64 * 60
+}
+
+/// The message an alert was raised by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMessage {
+    /// `type` of the `PrintJSON` message.
+    pub kind: String,
+    /// The message with its names resolved, as in the log.
+    pub text: String,
+}
+
+impl SourceMessage {
+    pub fn new(session: &Session, print: &PrintJSON) -> Self {
+        Self {
+            kind: print.kind().to_owned(),
+            text: session.render(print.data()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub kind: AlertKind,
+    /// See [`Alert::source`].
+    #[serde(default)]
+    pub source: String,
+    pub text: String,
+    /// Unset for alerts that no single message raised, such as summaries.
+    #[serde(default)]
+    pub message: Option<SourceMessage>,
+    /// When the alert fired, in seconds since the Unix epoch.
+    pub fired: u64,
+    #[serde(default)]
+    pub read: bool,
+    #[serde(default)]
+    pub acknowledged: bool,
+    /// Hidden until then, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub snoozed_until: Option<u64>,
+    /// See [`Alert::log_index`]. Not saved, the log starts over with every
+    /// run.
+    #[serde(skip)]
+    pub log_index: Option<u64>,
+}
+
+impl Entry {
+    pub fn is_snoozed(&self, now: u64) -> bool {
+        self.snoozed_until.is_some_and(|until| until > now)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Inbox {
+    /// File the inbox is saved to, unset until the slot connects or when the
+    /// config folder is unknown.
+    path: Option<PathBuf>,
+    pub entries: Vec<Entry>,
+    changed: bool,
+}
+
+impl Inbox {
+    /// The file of the inbox of `slot` in the room of `seed`.
+    pub fn path(dir: &Path, seed: Option<&str>, slot: &str) -> PathBuf {
+        let name: String = format!("{}-{}", seed.unwrap_or("unknown"), slot)
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        dir.join(format!("{}.json", name))
+    }
+
+    pub fn is_at(&self, path: &Path) -> bool {
+        self.path.as_deref() == Some(path)
+    }
+
+    /// Loads the inbox saved at `path`, empty if there is none yet.
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("could not read the inbox {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err).with_context(|| format!("could not read the inbox {}", path.display())),
+        };
+        Ok(Self {
+            path: Some(path),
+            entries,
+            changed: false,
+        })
+    }
+
+    /// Writes the inbox if it changed since it was last written.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let Some(path) = self.path.as_ref().filter(|_| self.changed) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("could not create {}", dir.display()))?;
+        }
+        let json = serde_json::to_vec_pretty(&self.entries)?;
+        config::write_atomic(path, &json).with_context(|| format!("could not write the inbox {}", path.display()))?;
+        self.changed = false;
+        Ok(())
+    }
+
+    pub fn has_changed(&self) -> bool {
+        self.changed && self.path.is_some()
+    }
+
+    pub fn push(&mut self, alert: &Alert, message: Option<SourceMessage>, fired: u64) {
+        self.entries.push(Entry {
+            kind: alert.kind,
+            source: alert.source.clone(),
+            text: alert.text.clone(),
+            message,
+            fired,
+            read: false,
+            acknowledged: false,
+            snoozed_until: None,
+            log_index: alert.log_index,
+        });
+        if self.entries.len() > INBOX_LIMIT {
+            self.entries.remove(0);
+        }
+        self.changed = true;
+    }
+
+    /// Changes the entry at `index`, which marks the inbox to be saved.
+    pub fn update(&mut self, index: usize, change: impl FnOnce(&mut Entry)) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            change(entry);
+            self.changed = true;
+        }
+    }
+
+    pub fn mark_all_read(&mut self) {
+        for entry in self.entries.iter_mut().filter(|entry| !entry.read) {
+            entry.read = true;
+            self.changed = true;
+        }
+    }
+
+    /// Hides the entry at `index` until `until`, when it comes back unread.
+    pub fn snooze(&mut self, index: usize, until: u64) {
+        self.update(index, |entry| {
+            entry.snoozed_until = Some(until);
+            entry.read = false;
+        });
+    }
+
+    /// Entries that are neither read, acknowledged nor snoozed.
+    pub fn unread(&self, now: u64) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.read && !entry.acknowledged && !entry.is_snoozed(now))
+            .count()
+    }
+}
+
+/// Local date and time of `secs` seconds since the Unix epoch.
+pub fn format_time(secs: u64) -> String {
+    let Ok(time) = OffsetDateTime::from_unix_timestamp(secs as i64) else {
+        return String::new();
+    };
+    let time = time.to_offset(throttle::local_offset());
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        time.year(),
+        time.month() as u8,
+        time.day(),
+        time.hour(),
+        time.minute()
+    )
+}
+
+/// Snoozes until the next time the local clock reads `time`, as `HH:MM`.
+pub fn snooze_until(time: &str) -> Option<u64> {
+    let minute = throttle::parse_time(time)?;
+    Some(next_time(now(), throttle::local_minute(), minute))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn saves_and_loads_entries() {
+        let dir = std::env::temp_dir().join(format!("apalert-inbox-{}", std::process::id()));
+        let path = Inbox::path(&dir, Some("1234"), "Alice/B");
+        assert_eq!(path.file_name().unwrap(), "1234-Alice_B.json");
+
+        let mut inbox = Inbox::load(path.clone()).unwrap();
+        let alert = Alert {
+            kind: AlertKind::Hint,
+            source: String::from("Room"),
+            text: String::from("Hookshot is at Bob's house"),
+            at: Instant::now(),
+            log_index: Some(3),
+        };
+        let message = SourceMessage {
+            kind: String::from("Hint"),
+            text: String::from("[Hint]: Alice's Hookshot is at Bob's house in Bob's World."),
+        };
+        inbox.push(&alert, Some(message.clone()), 100);
+        inbox.update(0, |entry| entry.snoozed_until = Some(200));
+        assert_eq!(inbox.unread(150), 0);
+        inbox.save().unwrap();
+        assert!(!inbox.has_changed());
+
+        let loaded = Inbox::load(path).unwrap();
+        assert_eq!(loaded.entries[0].log_index, None);
+        assert_eq!((loaded.entries[0].source.as_str(), &loaded.entries[0].message), ("Room", &Some(message)));
+        assert_eq!(loaded.unread(200), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn snoozes_until_the_next_time() {
+        // 10:00:30 local time, UTC here.
+        let now = 10 * 3600 + 30;
+        assert_eq!(next_time(now, 10 * 60, 11 * 60), 11 * 3600);
+        assert_eq!(next_time(now, 10 * 60, 9 * 60), 33 * 3600);
+        assert_eq!(next_time(now, 10 * 60, 10 * 60), 34 * 3600);
+    }
+}
@@ -1,16 +1,67 @@
+use std::sync::Mutex;
+
+use clap::Parser;
+use context::Context;
 use iced::{Application, Settings};
 use page::Page;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 
+mod alert;
+mod api;
+mod appearance;
 mod ap;
+mod cli;
+mod config;
+mod context;
+mod headless;
+mod inbox;
+mod mention;
+mod metrics;
+mod overlay;
 mod page;
+mod profile;
+mod secrets;
+mod session;
+mod sink;
+mod throttle;
+mod tui;
+mod update;
+mod watchlist;
+mod window_state;
+
+fn main() -> anyhow::Result<()> {
+    throttle::init_local_offset();
+    let cli = cli::Cli::parse();
+    let writer = if cli.tui {
+        BoxMakeWriter::new(Mutex::new(tui::log_file()?))
+    } else {
+        BoxMakeWriter::new(std::io::stderr)
+    };
 
-fn main() -> iced::Result {
     let subscriber = tracing_subscriber::fmt()
         .compact()
         .with_file(true)
         .with_line_number(true)
+        .with_ansi(!cli.tui)
+        .with_writer(writer)
         .finish();
     tracing::subscriber::set_global_default(subscriber).unwrap();
     
-    Page::run(Settings::default())
+    if cli.headless {
+        return headless::run(cli);
+    }
+    if cli.tui {
+        return tui::run(cli);
+    }
+
+    let context = Context::try_load_from_save();
+    Page::run(Settings {
+        window: if cli.overlay {
+            context.overlay.settings()
+        } else {
+            context.window.settings()
+        },
+        ..Settings::with_flags((cli, context))
+    })?;
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+//! Chat messages naming the slot or a keyword of its profile, and players
+//! whose chat is ignored.
+
+use serde::{Deserialize, Serialize};
+
+/// Chat settings of a profile. Both lists are kept as typed, blank entries
+/// are skipped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mentions {
+    /// Words raising a mention alert, on top of the slot name and alias.
+    pub keywords: Vec<String>,
+    /// Slot names or aliases of the players whose chat is ignored.
+    pub muted: Vec<String>,
+}
+
+impl Mentions {
+    /// Whether a player going by any of `names` is muted.
+    pub fn is_muted<'a>(&self, mut names: impl Iterator<Item = &'a str>) -> bool {
+        names.any(|name| {
+            self.muted
+                .iter()
+                .any(|muted| !muted.trim().is_empty() && muted.trim().eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Mutes a player by the first of `names`, or unmutes them by any.
+    pub fn set_muted(&mut self, names: &[&str], muted: bool) {
+        self.muted
+            .retain(|entry| !names.iter().any(|name| entry.trim().eq_ignore_ascii_case(name)));
+        if let Some(name) = names.first().filter(|_| muted) {
+            self.muted.push((*name).to_owned());
+        }
+    }
+
+    /// Whether `message` names one of `names` or a keyword. Names count with
+    /// or without a leading `@`.
+    pub fn is_mention<'a>(&self, message: &str, names: impl IntoIterator<Item = &'a str>) -> bool {
+        names.into_iter().any(|name| contains_word(message, name.trim()))
+            || self.keywords.iter().any(|keyword| contains_word(message, keyword.trim()))
+    }
+}
+
+/// Whether `word` appears in `text` on its own, ignoring case, rather than
+/// inside a longer word.
+pub fn contains_word(text: &str, word: &str) -> bool {
+    if word.is_empty() {
+        return false;
+    }
+    let (text, word) = (text.to_lowercase(), word.to_lowercase());
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    text.match_indices(&word).any(|(start, _)| {
+        !is_word(text[..start].chars().next_back()) && !is_word(text[start + word.len()..].chars().next())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_whole_words() {
+        assert!(contains_word("hey @alice, got your sword", "Alice"));
+        assert!(contains_word("Alice", "alice"));
+        assert!(!contains_word("malice", "Alice"));
+        assert!(!contains_word("alice_2 is here", "Alice"));
+        assert!(contains_word("is Link's Hookshot out?", "link"));
+        assert!(!contains_word("anything", ""));
+    }
+
+    #[test]
+    fn mutes_and_mentions() {
+        let mut mentions = Mentions {
+            keywords: vec![String::from("bk mode"), String::from(" ")],
+            muted: vec![String::from(" Bot ")],
+        };
+        assert!(mentions.is_muted(["Player 3", "bot"].into_iter()));
+        mentions.set_muted(&["Player 3", "BOT"], false);
+        mentions.set_muted(&["Spammer", "Spam"], true);
+        assert_eq!(mentions.muted, [String::from("Spammer")]);
+
+        assert!(mentions.is_mention("we are in BK mode", ["Alice"]));
+        assert!(mentions.is_mention("@Alice check this", ["Alice", "Ali"]));
+        assert!(!mentions.is_mention("nothing here", ["Alice"]));
+    }
+}
@@ -0,0 +1,294 @@
+//! Counters kept by each session, exposed in the Prometheus text format on
+//! `GET /metrics` of the local API.
+//!
+//! Locations and hint points of the session's own slot come from `Connected`
+//! and `RoomUpdate`, the client status of every slot of the team from the
+//! data storage. Other players are only seen through the messages the server
+//! sends to the session: the `ItemSend` messages involving the slot, so the
+//! item counters are named after what was seen rather than every check made.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use crate::ap::connection::ConnectionId;
+use crate::ap::messages::{Connected, PrintJSON};
+use crate::session::{Session, CLIENT_CONNECTED, CLIENT_GOAL};
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// `Connected` messages received, every one after the first being a
+    /// reconnect.
+    pub connections: u64,
+    pub parse_failures: u64,
+    /// `ItemSend` messages seen, by slot finding the item.
+    pub checks: BTreeMap<u32, u64>,
+    /// Items seen found by a slot for another one.
+    pub items_sent: BTreeMap<u32, u64>,
+    /// Items seen found by another slot for this one.
+    pub items_received: BTreeMap<u32, u64>,
+    /// `ClientStatus` of each slot of the team, from the data storage.
+    pub client_statuses: BTreeMap<u32, u64>,
+}
+
+impl Stats {
+    pub fn reconnects(&self) -> u64 {
+        self.connections.saturating_sub(1)
+    }
+
+    /// Counts a message of the room.
+    pub fn record(&mut self, print: &PrintJSON) {
+        if let PrintJSON::ItemSend { receiving, item, .. } = print {
+            *self.checks.entry(item.player).or_default() += 1;
+            if *receiving != item.player {
+                *self.items_sent.entry(item.player).or_default() += 1;
+                *self.items_received.entry(*receiving).or_default() += 1;
+            }
+        }
+    }
+
+    /// Slots of the team with a client connected. The server sets a slot
+    /// back to unknown once its last client leaves, but keeps the goal
+    /// status, so slots that completed their goal are not counted.
+    pub fn connected_players(&self) -> usize {
+        self.client_statuses
+            .values()
+            .filter(|status| (CLIENT_CONNECTED..CLIENT_GOAL).contains(*status))
+            .count()
+    }
+}
+
+/// Writes one metric family: its help, its type and a sample per label set.
+struct Family<'a> {
+    out: &'a mut String,
+}
+
+impl Family<'_> {
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+            .collect();
+        let _ = writeln!(self.out, "{}{{{}}} {}", name, labels.join(","), value);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Name and game of a slot, as labels.
+fn player(session: &Session, slot: u32) -> (String, String) {
+    let name = session
+        .player_name(slot)
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("Player {}", slot));
+    (name, session.game(slot).unwrap_or_default().to_owned())
+}
+
+/// Every metric of every session, in the Prometheus text format. Each family
+/// is written whole, its samples right after its own header.
+pub fn render(sessions: &BTreeMap<ConnectionId, Session>) -> String {
+    let mut out = String::new();
+    let mut family = Family { out: &mut out };
+
+    family.header("apalert_connected", "gauge", "Whether the session is connected to its room.");
+    for session in sessions.values() {
+        family.sample("apalert_connected", &[("session", session.label())], u8::from(session.room.is_some()));
+    }
+
+    type Locations = fn(&Connected) -> usize;
+    let locations: [(&str, &str, Locations); 2] = [
+        ("apalert_locations_checked", "Locations checked by the slot of the session.", |room| {
+            room.checked_locations.len()
+        }),
+        ("apalert_locations", "Locations of the slot of the session.", |room| {
+            room.checked_locations.len() + room.missing_locations.len()
+        }),
+    ];
+    for (name, help, count) in locations {
+        family.header(name, "gauge", help);
+        for session in sessions.values() {
+            let Some(room) = &session.room else {
+                continue;
+            };
+            let (player, game) = player(session, room.slot);
+            family.sample(name, &[("session", session.label()), ("player", &player), ("game", &game)], count(room));
+        }
+    }
+
+    type Counts = fn(&Stats) -> &BTreeMap<u32, u64>;
+    let counters: [(&str, &str, Counts); 3] = [
+        (
+            "apalert_seen_checks_total",
+            "Items found by each player, in the ItemSend messages the session received.",
+            |stats| &stats.checks,
+        ),
+        (
+            "apalert_seen_items_sent_total",
+            "Items found by each player for another one, in the ItemSend messages the session received.",
+            |stats| &stats.items_sent,
+        ),
+        (
+            "apalert_seen_items_received_total",
+            "Items received by each player from another one, in the ItemSend messages the session received.",
+            |stats| &stats.items_received,
+        ),
+    ];
+    for (name, help, counts) in counters {
+        family.header(name, "counter", help);
+        for session in sessions.values() {
+            for (slot, count) in counts(&session.stats) {
+                let (player, game) = player(session, *slot);
+                family.sample(name, &[("session", session.label()), ("player", &player), ("game", &game)], count);
+            }
+        }
+    }
+
+    family.header(
+        "apalert_client_status",
+        "gauge",
+        "ClientStatus of each player of the team: 0 unknown, 5 connected, 10 ready, 20 playing, 30 goal.",
+    );
+    for session in sessions.values() {
+        for (slot, status) in &session.stats.client_statuses {
+            let (player, game) = player(session, *slot);
+            let labels = [("session", session.label()), ("player", &player), ("game", &game)];
+            family.sample("apalert_client_status", &labels, status);
+        }
+    }
+
+    family.header("apalert_hints", "gauge", "Hints involving the slot of the session.");
+    for session in sessions.values() {
+        for found in [false, true] {
+            let count = session.hints.iter().filter(|hint| hint.found == found).count();
+            family.sample("apalert_hints", &[("session", session.label()), ("found", &found.to_string())], count);
+        }
+    }
+
+    family.header("apalert_hint_points", "gauge", "Hint points of the slot of the session.");
+    for session in sessions.values() {
+        if let Some(room) = &session.room {
+            family.sample("apalert_hint_points", &[("session", session.label())], room.hint_points);
+        }
+    }
+
+    type Value = fn(&Stats) -> u64;
+    let totals: [(&str, &str, &str, Value); 3] = [
+        (
+            "apalert_connected_players",
+            "gauge",
+            "Players of the team with a client connected, by client status, not counting those at their goal.",
+            |stats| stats.connected_players() as u64,
+        ),
+        ("apalert_reconnects_total", "counter", "Times the session connected again to its room.", |stats| {
+            stats.reconnects()
+        }),
+        ("apalert_parse_failures_total", "counter", "Server messages that could not be read.", |stats| {
+            stats.parse_failures
+        }),
+    ];
+    for (name, kind, help, value) in totals {
+        family.header(name, kind, help);
+        for session in sessions.values() {
+            family.sample(name, &[("session", session.label())], value(&session.stats));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::ap::connection::{Event, InputMessage, Source};
+    use crate::profile::Profile;
+
+    fn print(value: serde_json::Value) -> PrintJSON {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// A session connected to slot 1 of a room of two players.
+    fn session(name: &str) -> Session {
+        let profile = Profile {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        let mut session = Session::new(profile, Source::Live { record: None });
+        let connected = json!({
+            "cmd": "Connected",
+            "team": 0,
+            "slot": 1,
+            "players": [
+                { "team": 0, "slot": 1, "alias": "Alice", "name": "Alice" },
+                { "team": 0, "slot": 2, "alias": "Bob", "name": "Bob" },
+                { "team": 1, "slot": 3, "alias": "Carol", "name": "Carol" },
+            ],
+            "missing_locations": [3],
+            "checked_locations": [1, 2],
+            "hint_points": 4,
+            "slot_info": {},
+        });
+        session.process(&Event::APMessage(serde_json::from_value(connected).unwrap()));
+        session
+    }
+
+    #[test]
+    fn counts_items_and_clients() {
+        let mut stats = Stats::default();
+        let item = |player| json!({"item": 1, "location": 2, "player": player, "flags": 0});
+        stats.record(&print(json!({"type": "ItemSend", "data": [], "receiving": 2, "item": item(1)})));
+        stats.record(&print(json!({"type": "ItemSend", "data": [], "receiving": 1, "item": item(1)})));
+        assert_eq!(stats.checks[&1], 2);
+        assert_eq!((stats.items_sent[&1], stats.items_received[&2]), (1, 1));
+        assert!(!stats.items_sent.contains_key(&2));
+
+        // Players already connected send no `Join`, their status is read.
+        let mut session = session("Room");
+        assert!(matches!(session.outbox.last(), Some(InputMessage::Send(messages))
+            if format!("{:?}", messages).contains("_read_client_status_0_2")));
+        let retrieved = json!({
+            "cmd": "Retrieved",
+            "keys": { "_read_client_status_0_1": 20, "_read_client_status_0_2": 30, "_read_client_status_1_3": 20 },
+        });
+        session.process(&Event::APMessage(serde_json::from_value(retrieved).unwrap()));
+        assert_eq!(session.stats.connected_players(), 1);
+        assert!(!session.goal);
+
+        let set_reply = json!({ "cmd": "SetReply", "key": "_read_client_status_0_1", "value": 0, "original_value": 20 });
+        session.process(&Event::APMessage(serde_json::from_value(set_reply).unwrap()));
+        assert_eq!(session.stats.connected_players(), 0);
+    }
+
+    #[test]
+    fn writes_each_family_in_one_group() {
+        let sessions: BTreeMap<_, _> = [(ConnectionId(0), session("A")), (ConnectionId(1), session("B"))].into();
+        let out = render(&sessions);
+        assert!(out.contains("apalert_locations{session=\"A\",player=\"Alice\",game=\"\"} 3\n"));
+
+        let mut names: Vec<&str> = Vec::new();
+        for line in out.lines() {
+            let name = match line.strip_prefix("# ") {
+                Some(comment) => comment.split(' ').nth(1).unwrap(),
+                None => line.split('{').next().unwrap(),
+            };
+            if names.last() != Some(&name) {
+                assert!(!names.contains(&name), "{} is split", name);
+                names.push(name);
+            }
+        }
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        let mut out = String::new();
+        Family { out: &mut out }.sample("apalert_connected", &[("session", "a \"b\"\\")], 1);
+        assert_eq!(out, "apalert_connected{session=\"a \\\"b\\\"\\\\\"} 1\n");
+    }
+}
@@ -0,0 +1,114 @@
+//! Compact always-on-top window showing only the latest alerts, saved in the
+//! config.
+//!
+//! iced does not expose cursor hit testing, so the overlay cannot let clicks
+//! through to the windows below it. It is kept borderless and small instead,
+//! and can be dragged anywhere on screen.
+
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use iced::window::{self, Level};
+use iced::Size;
+use serde::{Deserialize, Serialize};
+
+use crate::session::Countdown;
+use crate::window_state;
+
+pub const ALERT_COUNTS: RangeInclusive<u8> = 1..=10;
+
+/// Seconds an alert stays in the overlay.
+pub const FADE_TIMEOUTS: RangeInclusive<u32> = 5..=120;
+
+/// Last part of the timeout, over which alerts fade out.
+const FADE_DURATION: Duration = Duration::from_secs(2);
+
+/// How long a countdown stays shown after its last number.
+const COUNTDOWN_LINGER: Duration = Duration::from_secs(3);
+
+const DEFAULT_SIZE: (u32, u32) = (360, 240);
+/// Smallest size of the window in either mode, iced cannot change it once
+/// the window is open.
+pub const MIN_SIZE: (u32, u32) = (200, 100);
+const MAX_SIZE: (u32, u32) = (1920, 1080);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Overlay {
+    /// Number of alerts shown at once.
+    pub alert_count: u8,
+    /// Seconds after which an alert is gone.
+    pub fade_timeout: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Centered while `None`, until the overlay is first moved. Not saved,
+    /// see [`window_state`].
+    #[serde(skip)]
+    pub position: Option<(i32, i32)>,
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self {
+            alert_count: 5,
+            fade_timeout: 15,
+            width: DEFAULT_SIZE.0,
+            height: DEFAULT_SIZE.1,
+            position: None,
+        }
+    }
+}
+
+impl Overlay {
+    pub fn alert_count(&self) -> usize {
+        self.alert_count.clamp(*ALERT_COUNTS.start(), *ALERT_COUNTS.end()) as usize
+    }
+
+    fn fade_timeout(&self) -> Duration {
+        Duration::from_secs(self.fade_timeout.clamp(*FADE_TIMEOUTS.start(), *FADE_TIMEOUTS.end()) as u64)
+    }
+
+    /// Opacity of an alert received `age` ago, `None` once it has faded out.
+    pub fn opacity(&self, age: Duration) -> Option<f32> {
+        let remaining = self.fade_timeout().checked_sub(age).filter(|remaining| !remaining.is_zero())?;
+        Some((remaining.as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.0))
+    }
+
+    /// Borderless and always on top.
+    pub fn settings(&self) -> window::Settings {
+        let width = self.width.clamp(MIN_SIZE.0, MAX_SIZE.0);
+        let height = self.height.clamp(MIN_SIZE.1, MAX_SIZE.1);
+        window::Settings {
+            size: Size::new(width as f32, height as f32),
+            min_size: Some(Size::new(MIN_SIZE.0 as f32, MIN_SIZE.1 as f32)),
+            position: window_state::position(self.position),
+            decorations: false,
+            level: Level::AlwaysOnTop,
+            exit_on_close_request: false,
+            ..Default::default()
+        }
+    }
+}
+
+/// Whether a countdown is still worth showing at `now`.
+pub fn countdown_visible(countdown: &Countdown, now: Instant) -> bool {
+    now.saturating_duration_since(countdown.at) < COUNTDOWN_LINGER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts_fade_out_at_the_end_of_the_timeout() {
+        let overlay = Overlay {
+            fade_timeout: 10,
+            ..Default::default()
+        };
+        assert_eq!(overlay.opacity(Duration::ZERO), Some(1.0));
+        assert_eq!(overlay.opacity(Duration::from_secs(7)), Some(1.0));
+        assert_eq!(overlay.opacity(Duration::from_secs(9)), Some(0.5));
+        assert_eq!(overlay.opacity(Duration::from_secs(10)), None);
+        assert_eq!(overlay.opacity(Duration::from_secs(60)), None);
+    }
+}
@@ -1,141 +1,214 @@
+mod alerts;
 mod auth;
 mod dashboard;
+mod data_storage;
+mod hints;
+mod overlay;
+mod settings;
 
-use std::path::PathBuf;
+use iced::widget::{button, column, container, row, text, Column, Row, Space};
+use std::time::Duration;
 
-use iced::{executor, Application, Command, Element, Theme};
-use serde::{Deserialize, Serialize};
-use tracing::info;
+use iced::window::Position;
+use iced::{event, executor, time, window, Alignment, Application, Command, Element, Event, Length, Subscription, Theme};
 
-use crate::ap::connection::{self, connect, ConnectionInfo};
-use auth::Auth;
-use dashboard::Dashboard;
+use crate::ap::connection::connect;
+use crate::cli::Cli;
+use crate::context::Context;
+use crate::inbox;
+use crate::throttle;
+use crate::update::{Message, Route, Screen, State};
 
-#[derive(Default, Debug, Deserialize, Serialize)]
-pub struct Context {
-    pub connection_info: ConnectionInfo,
-    #[serde(skip)]
-    pub worker_channel: Option<connection::Connection>,
-}
+/// How often the overlay is redrawn while alerts fade out.
+const OVERLAY_TICK: Duration = Duration::from_millis(100);
 
 pub struct Page {
-    context: Context,
-    cur_view: Box<dyn View>,
+    state: State,
 }
 
-#[derive(Debug, Clone)]
-pub enum Pages {
-    Connection,
-    Dashboard,
-}
+fn tab<'a>(label: String, selected: bool, message: Message) -> Element<'a, Message> {
+    let style = if selected {
+        iced::theme::Button::Primary
+    } else {
+        iced::theme::Button::Secondary
+    };
 
-#[derive(Debug, Clone)]
-pub enum Message {
-    PseudoInputChanged(String),
-    ServerIPInputChanged(String),
-    ServerPortInputChanged(String),
-    ServerPasswordInputChanged(String),
-    Error(String),
-    ChangePage(Pages),
-    WSEvent(connection::Event),
-    Connect,
+    button(text(label)).style(style).on_press(message).into()
 }
 
-const QUALIFIER: &'static str = "pw";
-const ORGANIZATION: &'static str = "olympus_inc";
-const APPLICATION: &'static str = "APAlert";
-const CONFIG_FILE_NAME: &'static str = "config.json";
-
-fn get_config_path() -> PathBuf {
-    let path = directories::ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION).unwrap();
-
-    std::fs::create_dir_all(path.config_dir()).unwrap();
-    path.config_dir().join(CONFIG_FILE_NAME)
-}
-
-impl Context {
-    fn try_load_from_save() -> Self {
-        match std::fs::File::open(get_config_path()) {
-            Ok(file) => {
-                match serde_json::from_reader(file) {
-                    Ok(s) => s,
-                    Err(_) => Self::default(),
-                }
-            },
-            Err(_) => {
-                info!("Could not load save File, using default");
-                Self::default()
-            },
+fn window_event(event: Event, _: event::Status) -> Option<Message> {
+    match event {
+        Event::Window(window::Id::MAIN, window::Event::Resized { width, height }) => {
+            Some(Message::WindowResized { width, height })
         }
+        Event::Window(window::Id::MAIN, window::Event::Moved { x, y }) => Some(Message::WindowMoved { x, y }),
+        Event::Window(window::Id::MAIN, window::Event::CloseRequested) => Some(Message::WindowCloseRequested),
+        _ => None,
     }
+}
 
-    fn save(&self) {
-        let file = std::fs::File::create(get_config_path()).unwrap();
-
-        serde_json::to_writer_pretty(file, &self).unwrap();
+/// Moves the window in or out of the overlay, to the size and position
+/// kept for the mode it is now in.
+fn toggle_overlay(state: &State) -> Command<Message> {
+    let settings = if state.overlay {
+        state.context.overlay.settings()
+    } else {
+        state.context.window.settings()
+    };
+    let mut commands = vec![
+        window::maximize(window::Id::MAIN, false),
+        window::toggle_decorations(window::Id::MAIN),
+        window::change_level(window::Id::MAIN, settings.level),
+        window::resize(window::Id::MAIN, settings.size),
+    ];
+    if let Position::Specific(position) = settings.position {
+        commands.push(window::move_to(window::Id::MAIN, position));
     }
+    Command::batch(commands)
 }
 
-pub trait View {
-    fn title(&self) -> String;
-    fn update(&mut self, message: Message, context: &mut Context) -> Command<Message>;
-    fn view(&self, context: &Context) -> Element<Message>;
+/// Back button, a button per route and the disconnect button.
+fn navigation(state: &State) -> Element<'_, Message> {
+    let current = state.screen.route();
+    let now = inbox::now();
+    let unread: usize = state.context.sessions.values().map(|session| session.inbox.unread(now)).sum();
+    Route::ALL
+        .into_iter()
+        .fold(
+            Row::new()
+                .spacing(5)
+                .push(button("Back").on_press_maybe((!state.history.is_empty()).then_some(Message::Back))),
+            |row, route| {
+                let label = match route {
+                    Route::Alerts if unread > 0 => format!("{} ({})", route, unread),
+                    _ => route.to_string(),
+                };
+                row.push(tab(label, route == current, Message::Navigate(route)))
+            },
+        )
+        .push(Space::with_width(Length::Fill))
+        .push(button("Overlay").on_press(Message::ToggleOverlay))
+        .push(button("Disconnect").on_press_maybe(state.context.has_sessions().then_some(Message::Disconnect)))
+        .into()
 }
 
 impl Application for Page {
     type Message = Message;
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
-        (
-            Self {
-                context: Context::try_load_from_save(),
-                cur_view: Box::new(Auth {}),
-            },
-            Command::none(),
-        )
+    fn new((cli, mut context): Self::Flags) -> (Self, Command<Self::Message>) {
+        if let Err(err) = context.apply_cli(&cli) {
+            context.report(err);
+        }
+        let command = if context.window.maximized && !cli.overlay {
+            window::maximize(window::Id::MAIN, true)
+        } else {
+            Command::none()
+        };
+
+        let mut state = State::new(context);
+        state.overlay = cli.overlay;
+        (Self { state }, command)
     }
 
     fn theme(&self) -> Self::Theme {
-        Theme::Dracula
+        self.state.context.appearance.theme()
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.state.context.appearance.text_scale()
     }
 
     fn title(&self) -> String {
-        self.cur_view.title()
+        String::from("AP_Alert")
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
-        match message {
-            Message::ChangePage(page) => {
-                match page {
-                    Pages::Connection => {
-                        self.cur_view = Box::new(Auth {});
-                    },
-                    Pages::Dashboard => {
-                        self.cur_view = Box::new(Dashboard {});
-                    },
-                }
-                Command::none()
-            },
-            Message::WSEvent(connection::Event::WorkerReady(con)) => {
-                self.context.worker_channel.replace(con);
-
-                Command::none()
-            },
-            _ => self.cur_view.update(message, &mut self.context)
+        let command = match message {
+            Message::WindowCloseRequested => window::fetch_maximized(window::Id::MAIN, Message::WindowClosing),
+            Message::WindowClosing(_) => window::close(window::Id::MAIN),
+            Message::DragOverlay => window::drag(window::Id::MAIN),
+            _ => Command::none(),
+        };
+        let toggled = matches!(message, Message::ToggleOverlay);
+        let jumped = matches!(message, Message::JumpToAlert(..));
+        self.state.dispatch(message);
+
+        match &self.state.screen {
+            _ if toggled => Command::batch([command, toggle_overlay(&self.state)]),
+            Screen::Dashboard(dashboard) if jumped => {
+                Command::batch([command, dashboard::scroll_to_highlight(dashboard, &self.state.context)])
+            }
+            _ => command,
         }
     }
 
-    fn view(&self) -> Element<Message> {
-        self.cur_view.view(&self.context)
+    fn view(&self) -> Element<'_, Message> {
+        if self.state.overlay {
+            return overlay::view(&self.state);
+        }
+
+        let context = &self.state.context;
+        let density = context.appearance.density;
+        let errors = context.errors.iter().enumerate().fold(
+            Column::new().spacing(5).padding(5),
+            |col, (index, err)| {
+                col.push(
+                    row![
+                        text(err)
+                            .width(Length::Fill)
+                            .style(iced::theme::Text::Color(iced::Color::from_rgb(1.0, 0.33, 0.33))),
+                        button("Dismiss").on_press(Message::DismissError(index)),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                )
+            },
+        );
+
+        let screen = match &self.state.screen {
+            Screen::Login(login) => auth::view(login, context),
+            Screen::Dashboard(dashboard) => dashboard::view(dashboard, context),
+            Screen::Hints => container(hints::view(context)).padding(density.space(20)).into(),
+            Screen::Alerts(alerts) => container(alerts::view(alerts, context)).padding(density.space(20)).into(),
+            Screen::DataStorage(data_storage) => container(data_storage::view(data_storage, context))
+                .padding(density.space(20))
+                .into(),
+            Screen::Settings => container(settings::view(context)).padding(density.space(20)).into(),
+        };
+
+        let side = density.space(20);
+        column![
+            errors,
+            container(navigation(&self.state)).padding([density.space(10), side, 0, side]),
+            screen,
+        ]
+        .into()
     }
 
-    fn subscription(&self) -> iced::Subscription<Self::Message> {
-        connect().map(Message::WSEvent)
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let connections = Subscription::batch(
+            self.state
+                .context
+                .sessions
+                .iter()
+                .map(|(id, session)| connect(*id, session.source.clone())),
+        )
+            .map(|(id, event)| Message::WSEvent(id, event));
+
+        let tick = if self.state.overlay {
+            time::every(OVERLAY_TICK).map(Message::Tick)
+        } else if self.state.context.throttle.is_pending() {
+            time::every(throttle::TICK).map(Message::Tick)
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([connections, event::listen_with(window_event), tick])
     }
 
     type Executor = executor::Default;
 
     type Theme = Theme;
 
-    type Flags = ();
+    type Flags = (Cli, Context);
 }
@@ -0,0 +1,98 @@
+use iced::widget::{button, checkbox, column, row, scrollable, text, text_input, Column, Space};
+use iced::{Alignment, Element, Length};
+
+use crate::ap::connection::ConnectionId;
+use crate::context::Context;
+use crate::inbox::{self, Entry};
+use crate::throttle;
+use crate::update::{Alerts, Message};
+
+/// Minutes of the snooze buttons.
+const SNOOZES: [(&str, u64); 2] = [("1 h", 60), ("8 h", 8 * 60)];
+
+fn entry_row<'a>(alerts: &Alerts, id: ConnectionId, index: usize, source: &'a str, entry: &'a Entry) -> Element<'a, Message> {
+    let marker = if entry.read { " " } else { "●" };
+    let snooze_until = throttle::parse_time(&alerts.snooze_input).is_some();
+    let actions = SNOOZES
+        .into_iter()
+        .fold(
+            row![
+                text(marker).width(15),
+                text(inbox::format_time(entry.fired)).width(130),
+                text(format!("[{}] [{}] {}", source, entry.kind, entry.text)).width(Length::Fill),
+                button(if entry.read { "Unread" } else { "Read" })
+                    .on_press(Message::AlertReadToggled(id, index, !entry.read)),
+                button("Acknowledge").on_press_maybe((!entry.acknowledged).then_some(Message::AcknowledgeAlert(id, index))),
+            ],
+            |row, (label, minutes)| row.push(button(label).on_press(Message::SnoozeAlert(id, index, minutes))),
+        )
+        .push(button("Snooze until").on_press_maybe(snooze_until.then_some(Message::SnoozeAlertUntil(id, index))))
+        .push(button("Show in log").on_press_maybe(entry.log_index.map(|_| Message::JumpToAlert(id, index))))
+        .spacing(5)
+        .align_items(Alignment::Center);
+
+    // Fired by an earlier run, whose log is gone.
+    let message = entry
+        .message
+        .as_ref()
+        .filter(|_| entry.log_index.is_none())
+        .map(|message| row![Space::with_width(145), text(format!("{}: {}", message.kind, message.text)).size(14)]);
+    column![actions].push_maybe(message).spacing(2).into()
+}
+
+/// Alerts fired for the connected slots, newest first, including the ones
+/// saved by earlier runs.
+pub fn view<'a>(alerts: &'a Alerts, context: &'a Context) -> Element<'a, Message> {
+    let now = inbox::now();
+    let mut entries: Vec<_> = context
+        .sessions
+        .iter()
+        .flat_map(|(id, session)| {
+            session
+                .inbox
+                .entries
+                .iter()
+                .enumerate()
+                .map(move |(index, entry)| {
+                    // Entries saved before sources were kept have none.
+                    let source = if entry.source.is_empty() { session.label() } else { &entry.source };
+                    (*id, index, source, entry)
+                })
+        })
+        .filter(|(_, _, _, entry)| alerts.show_acknowledged || !entry.acknowledged)
+        .collect();
+    let snoozed = entries.iter().filter(|(_, _, _, entry)| entry.is_snoozed(now)).count();
+    entries.retain(|(_, _, _, entry)| !entry.is_snoozed(now));
+    entries.sort_by_key(|(_, _, _, entry)| std::cmp::Reverse(entry.fired));
+
+    let content: Element<Message> = if entries.is_empty() {
+        text("No alerts yet").into()
+    } else {
+        let list = entries.into_iter().fold(Column::new().spacing(5), |col, (id, index, source, entry)| {
+            col.push(entry_row(alerts, id, index, source, entry))
+        });
+        scrollable(list).width(Length::Fill).height(Length::Fill).into()
+    };
+
+    let held = context.throttle.held.len();
+    let held = (held > 0).then(|| text(format!("{} alerts held until do not disturb and quiet hours are over", held)));
+
+    let controls = row![
+        checkbox("Show acknowledged", alerts.show_acknowledged).on_toggle(Message::ShowAcknowledgedToggled),
+        text(format!("{} snoozed", snoozed)),
+        Space::with_width(Length::Fill),
+        text("Snooze until"),
+        text_input("08:00", &alerts.snooze_input)
+            .width(80)
+            .on_input(Message::SnoozeInputChanged),
+        button("Mark all read").on_press(Message::MarkAllAlertsRead),
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center);
+
+    column![text("Alerts").size(24), controls]
+        .push_maybe(held)
+        .push(content)
+        .spacing(context.appearance.density.space(20))
+        .into()
+}
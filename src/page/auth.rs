@@ -1,124 +1,207 @@
-use iced::widget::{button, column, row, text, text_input, Space};
-use iced::{Alignment, Command, Element, Length};
-use tracing::{error, info};
+use iced::widget::{button, checkbox, column, pick_list, row, text, text_input, Column, Space};
+use iced::{Alignment, Element, Length};
 
-use crate::ap::connection;
+use crate::alert::AlertKind;
+use crate::secrets::PasswordStorage;
+use crate::ap::address::ServerAddress;
+use crate::ap::connection::{
+    ITEMS_HANDLING_OTHER_WORLDS, ITEMS_HANDLING_OWN_WORLD, ITEMS_HANDLING_STARTING_INVENTORY,
+};
 
-use super::{Context, Message, Pages, View};
+use crate::context::Context;
+use crate::update::{Login, Message};
+use crate::watchlist::{Syntax, Watch};
 
-pub struct Auth {}
-
-impl View for Auth {
-    fn view(&self, context: &Context) -> Element<Message> {
-        iced::widget::container::Container::new(
-            column![
-                column![
-                    row![
-                        text("Slot: ")
-                            .width(100)
-                            .horizontal_alignment(iced::alignment::Horizontal::Right),
-                        text_input("Slot", &context.connection_info.slot)
-                            .width(300)
-                            .on_input(Message::PseudoInputChanged),
-                        Space::with_width(100)
-                    ]
-                    .align_items(Alignment::Center),
-                    row![
-                        text("IP: ")
-                            .width(100)
-                            .horizontal_alignment(iced::alignment::Horizontal::Right),
-                        text_input("IP", &context.connection_info.ip)
-                            .width(300)
-                            .on_input(Message::ServerIPInputChanged),
-                        Space::with_width(100)
-                    ]
-                    .align_items(Alignment::Center),
-                    row![
-                        text("Port: ")
-                            .width(100)
-                            .horizontal_alignment(iced::alignment::Horizontal::Right),
-                        text_input("Port", &context.connection_info.port)
-                            .width(300)
-                            .on_input(Message::ServerPortInputChanged),
-                        Space::with_width(100)
-                    ]
-                    .align_items(Alignment::Center),
-                    row![
-                        text("Password: ")
-                            .width(100)
-                            .horizontal_alignment(iced::alignment::Horizontal::Right),
-                        text_input("Password", &context.connection_info.password)
-                            .width(300)
-                            .on_input(Message::ServerPasswordInputChanged),
-                        Space::with_width(100)
-                    ]
-                    .align_items(Alignment::Center),
-                ]
-                .spacing(5),
-                button("Connect").on_press(Message::Connect)
-            ]
-            .align_items(Alignment::Center)
-            .spacing(20),
-        )
-        .center_y()
-        .center_x()
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .into()
-    }
-
-    fn title(&self) -> String {
-        String::from("AP_Alert")
-    }
-
-    fn update(
-        &mut self,
-        message: super::Message,
-        context: &mut Context,
-    ) -> iced::Command<super::Message> {
-        match message {
-            Message::PseudoInputChanged(updated_pseudo) => {
-                context.connection_info.slot = updated_pseudo;
+fn field<'a>(label: &str, input: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+    row![
+        text(format!("{}: ", label))
+            .width(100)
+            .horizontal_alignment(iced::alignment::Horizontal::Right),
+        input.into(),
+        Space::with_width(100)
+    ]
+    .align_items(Alignment::Center)
+    .into()
+}
 
-                Command::none()
-            }
-            Message::ServerIPInputChanged(updated_server_ip) => {
-                context.connection_info.ip = updated_server_ip;
+fn watch_row(index: usize, watch: &Watch) -> Element<'_, Message> {
+    let error = watch.compile().err().map(|err| {
+        text(err).style(iced::theme::Text::Color(iced::Color::from_rgb(1.0, 0.33, 0.33)))
+    });
+    row![
+        text_input("Item name", &watch.pattern)
+            .width(200)
+            .on_input(move |pattern| Message::WatchPatternChanged(index, pattern)),
+        pick_list(&Syntax::ALL[..], Some(watch.syntax), move |syntax| Message::WatchSyntaxSelected(index, syntax)),
+        text_input("Any game", &watch.game)
+            .width(150)
+            .on_input(move |game| Message::WatchGameChanged(index, game)),
+        button("Remove").on_press(Message::WatchRemoved(index)),
+    ]
+    .push_maybe(error)
+    .spacing(5)
+    .align_items(Alignment::Center)
+    .into()
+}
 
-                Command::none()
-            }
-            Message::ServerPortInputChanged(updated_port) => {
-                context.connection_info.port = updated_port;
+pub fn view<'a>(login: &'a Login, context: &'a Context) -> Element<'a, Message> {
+    let density = context.appearance.density;
+    let profile = context.profile();
+    let connection_info = &profile.connection_info;
+    let address_error = connection_info.server.parse::<ServerAddress>().err();
 
-                Command::none()
-            }
-            Message::ServerPasswordInputChanged(updated_password) => {
-                context.connection_info.password = updated_password;
+    let choices = context.profile_choices();
+    let selected = choices.get(context.selected_profile).cloned();
 
-                Command::none()
-            }
+    let items_handling = [
+        (ITEMS_HANDLING_OTHER_WORLDS, "Items from other worlds"),
+        (ITEMS_HANDLING_OWN_WORLD, "Items from own world"),
+        (ITEMS_HANDLING_STARTING_INVENTORY, "Starting inventory"),
+    ]
+    .into_iter()
+    .fold(Column::new().spacing(5), |col, (flag, label)| {
+        col.push(
+            checkbox(label, connection_info.options.items_handling & flag != 0)
+                .on_toggle(move |enabled| Message::ItemsHandlingToggled(flag, enabled)),
+        )
+    });
 
-            Message::Connect => {
-                info!("attempting connexion");
-                if let Some(c) = &mut context.worker_channel {
-                    c.send(connection::InputMessage::Connect(context.connection_info.clone()));
-                }
+    let alert_rules = AlertKind::ALL
+        .into_iter()
+        .fold(Column::new().spacing(5), |col, kind| {
+            col.push(
+                checkbox(kind.to_string(), profile.alert_rules.get(kind))
+                    .on_toggle(move |enabled| Message::AlertRuleToggled(kind, enabled)),
+            )
+        });
 
-                Command::none()
-            }
+    let watchlist = profile
+        .watchlist
+        .iter()
+        .enumerate()
+        .fold(Column::new().spacing(5), |col, (index, watch)| col.push(watch_row(index, watch)))
+        .push(button("Add item").on_press(Message::WatchAdded));
 
-            Message::WSEvent(connection::Event::APMessage(crate::ap::messages::APServerMessage::Connected(_))) => {
-                context.save();
-                info!("Logged in");
-                Command::perform(async{}, |_| Message::ChangePage(Pages::Dashboard))
-            }
+    let secrets: Element<Message> = match context.password_storage {
+        PasswordStorage::Encrypted if !context.secrets.is_unlocked() => {
+            let (placeholder, action) = if context.secrets.exists() {
+                ("Passphrase", "Unlock")
+            } else {
+                ("New passphrase", "Set passphrase")
+            };
+            row![
+                text_input(placeholder, &login.passphrase)
+                    .secure(true)
+                    .width(200)
+                    .on_input(Message::PassphraseInputChanged)
+                    .on_submit(Message::UnlockSecrets),
+                button(action).on_press(Message::UnlockSecrets),
+            ]
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .into()
+        }
+        PasswordStorage::Encrypted => text("Passwords are saved encrypted").into(),
+        PasswordStorage::Never => text("Passwords are kept until exit").into(),
+    };
 
-            Message::Error(err) => {
-                error!("{}", err);
+    let secrets_status = match (&login.secrets_error, context.plaintext_passwords) {
+        (Some(err), _) => err.clone(),
+        (None, true) => String::from("Your config still holds passwords in clear text, set a passphrase to encrypt them"),
+        (None, false) => String::new(),
+    };
 
-                Command::none()
-            }
-            _ => { Command::none() }
-        }
-    }
+    iced::widget::container::Container::new(
+        column![
+            row![
+                pick_list(&PasswordStorage::ALL[..], Some(context.password_storage), Message::PasswordStorageSelected),
+                secrets,
+            ]
+            .spacing(5)
+            .align_items(Alignment::Center),
+            text(secrets_status)
+                .style(iced::theme::Text::Color(iced::Color::from_rgb(1.0, 0.33, 0.33))),
+            row![
+                pick_list(choices, selected, Message::ProfileSelected).width(200),
+                button("New").on_press(Message::NewProfile),
+                button("Duplicate").on_press(Message::DuplicateProfile),
+                button("Delete").on_press(Message::DeleteProfile),
+            ]
+            .spacing(5)
+            .align_items(Alignment::Center),
+            column![
+                field(
+                    "Profile",
+                    text_input("Profile name", &profile.name)
+                        .width(300)
+                        .on_input(Message::ProfileNameInputChanged)
+                ),
+                field(
+                    "Slot",
+                    text_input("Slot", &connection_info.slot)
+                        .width(300)
+                        .on_input(Message::PseudoInputChanged)
+                ),
+                field(
+                    "Server",
+                    text_input("archipelago.gg:38281", &connection_info.server)
+                        .width(300)
+                        .on_input(Message::ServerAddressInputChanged)
+                ),
+                field(
+                    "Password",
+                    text_input("Password", &connection_info.password)
+                        .secure(true)
+                        .width(300)
+                        .on_input(Message::ServerPasswordInputChanged)
+                ),
+                field(
+                    "Game",
+                    text_input("Empty for trackers", &connection_info.options.game)
+                        .width(300)
+                        .on_input(Message::GameInputChanged)
+                ),
+                field(
+                    "Tags",
+                    text_input("Tracker,TextOnly", &connection_info.options.tags.join(","))
+                        .width(300)
+                        .on_input(Message::TagsInputChanged)
+                ),
+            ]
+            .spacing(5),
+            row![
+                column![
+                    text("Connect options"),
+                    checkbox("Request slot data", connection_info.options.slot_data)
+                        .on_toggle(Message::SlotDataToggled),
+                    items_handling,
+                ]
+                .spacing(5),
+                column![text("Alerts"), alert_rules].spacing(5),
+                column![
+                    text("Watched items"),
+                    watchlist,
+                    text("Chat mentions"),
+                    text_input("Keywords, comma separated", &profile.mentions.keywords.join(","))
+                        .width(300)
+                        .on_input(Message::MentionKeywordsChanged),
+                    text_input("Muted players, comma separated", &profile.mentions.muted.join(","))
+                        .width(300)
+                        .on_input(Message::MutedPlayersChanged),
+                ]
+                .spacing(5),
+            ]
+            .spacing(density.space(50)),
+            text(address_error.as_ref().map(|err| err.to_string()).unwrap_or_default())
+                .style(iced::theme::Text::Color(iced::Color::from_rgb(1.0, 0.33, 0.33))),
+            button("Connect").on_press_maybe(address_error.is_none().then_some(Message::Connect)),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(density.space(20)),
+    )
+    .center_y()
+    .center_x()
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .into()
 }
@@ -1,34 +1,295 @@
-use iced::{widget::row, Command};
-use iced::widget::{text, Space};
+use iced::widget::{button, column, container, row, scrollable, text, text_input, Column, Row, Space};
+use iced::{Alignment, Color, Command, Element, Length};
 
-use super::View;
+use crate::alert::Alert;
+use crate::ap::connection::ConnectionId;
+use crate::ap::messages::{Connected, PrintJSON, SlotType};
+use crate::session::{Section, Session};
 
-pub struct Dashboard {
+use crate::context::Context;
+use crate::update::{Dashboard, Message, Route};
+
+use super::tab;
+
+/// Scrollable of the log and chat sections.
+const LOG_ID: &str = "log";
+
+const COLUMN_WIDTHS: [u16; 6] = [50, 150, 150, 200, 100, 250];
+
+fn table_row<'a>(cells: [String; 6]) -> Element<'a, Message> {
+    let mut row = row![].spacing(10);
+    for (cell, width) in cells.into_iter().zip(COLUMN_WIDTHS) {
+        row = row.push(text(cell).width(width));
+    }
+    row.into()
 }
 
-impl View for Dashboard {
-    fn title(&self) -> String {
-        String::from("AP_Alert")
+fn player_table<'a>(id: ConnectionId, session: &Session, room: &'a Connected) -> Element<'a, Message> {
+    let mut table = Column::new().spacing(5).push(table_row([
+        "Slot".to_owned(),
+        "Name".to_owned(),
+        "Alias".to_owned(),
+        "Game".to_owned(),
+        "Type".to_owned(),
+        "Members".to_owned(),
+    ]));
+
+    for (slot, info) in &room.slot_info {
+        let alias = room
+            .players
+            .iter()
+            .find(|player| player.team == room.team && player.slot == *slot)
+            .map(|player| player.alias.clone())
+            .unwrap_or_default();
+        let members = match info.r#type {
+            SlotType::Group => info
+                .group_members
+                .iter()
+                .map(|member| {
+                    room.slot_info
+                        .get(member)
+                        .map(|member| member.name.clone())
+                        .unwrap_or_else(|| member.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => String::new(),
+        };
+
+        let muted = session.is_muted(*slot);
+        let mute = (*slot != room.slot).then(|| {
+            button(if muted { "Unmute" } else { "Mute" })
+                .on_press(Message::PlayerMuteToggled(id, *slot, !muted))
+        });
+        table = table.push(
+            row![table_row([
+                slot.to_string(),
+                info.name.clone(),
+                alias,
+                info.game.clone(),
+                info.r#type.to_string(),
+                members,
+            ])]
+            .push_maybe(mute)
+            .align_items(Alignment::Center),
+        );
     }
 
-    fn update(&mut self, message: super::Message, context: &mut super::Context) -> iced::Command<super::Message> {
-        match message {
-            _ => Command::none()
+    table.into()
+}
+
+fn alert_list<'a>(alerts: impl DoubleEndedIterator<Item = &'a Alert>) -> Element<'a, Message> {
+    let list = alerts.rev().fold(Column::new().spacing(5), |col, alert| {
+        col.push(text(format!("[{}] [{}] {}", alert.source, alert.kind, alert.text)))
+    });
+
+    column![
+        text("Alerts"),
+        scrollable(list).width(Length::Fill).height(Length::Fill),
+    ]
+    .spacing(5)
+    .into()
+}
+
+fn slot_data_inspector(room: &Connected) -> Element<'_, Message> {
+    let slot_data = match &room.slot_data {
+        Some(slot_data) => serde_json::to_string_pretty(slot_data).unwrap_or_default(),
+        None => String::from("No slot data"),
+    };
+
+    column![
+        text("Slot data"),
+        scrollable(text(slot_data).size(14))
+            .width(Length::Fill)
+            .height(Length::Fill),
+    ]
+    .spacing(5)
+    .into()
+}
+
+/// Lines newest first, with the one at `highlight` boxed and the flagged
+/// ones, see [`Session::highlighted_log`], drawn in `color`.
+fn message_log<'a>(
+    lines: impl Iterator<Item = (String, bool)>,
+    highlight: Option<usize>,
+    color: Color,
+) -> Element<'a, Message> {
+    let lines: Vec<_> = lines.collect();
+    let list = lines.into_iter().enumerate().rev().fold(Column::new().spacing(5), |col, (position, (line, flagged))| {
+        let line = if flagged {
+            text(line).style(iced::theme::Text::Color(color))
+        } else {
+            text(line)
+        };
+        if highlight == Some(position) {
+            col.push(container(line).padding(5).width(Length::Fill).style(iced::theme::Container::Box))
+        } else {
+            col.push(line)
+        }
+    });
+
+    scrollable(list).id(scrollable::Id::new(LOG_ID)).width(Length::Fill).height(Length::Fill).into()
+}
+
+/// Scrolls the log to the message shown by [`Message::JumpToAlert`].
+pub fn scroll_to_highlight(dashboard: &Dashboard, context: &Context) -> Command<Message> {
+    let session = dashboard.selected.and_then(|id| context.sessions.get(&id));
+    let position = session.and_then(|session| Some((session.log_position(dashboard.highlight?)?, session.log.len())));
+    match position {
+        Some((position, len)) if len > 1 => {
+            let y = (len - 1 - position) as f32 / (len - 1) as f32;
+            scrollable::snap_to(scrollable::Id::new(LOG_ID), scrollable::RelativeOffset { x: 0.0, y })
         }
+        _ => Command::none(),
     }
+}
 
-    fn view(&self, context: &super::Context) -> iced::Element<super::Message> {
-        iced::widget::container::Container::new(
-            row![
-                text(format!("Slot: {} - Server: {}:{}", context.connection_info.slot, context.connection_info.ip, context.connection_info.port))
-                    .horizontal_alignment(iced::alignment::Horizontal::Right),
-                Space::with_width(100)
+fn hint_list(session: &Session) -> Element<'_, Message> {
+    if session.hints.is_empty() {
+        return text("No hints yet").into();
+    }
 
-            ]
-        )
-        .center_y()
-        .center_x()
-        .into()
+    let list = session.hints.iter().fold(Column::new().spacing(5), |col, hint| {
+        col.push(text(session.hint_text(hint)))
+    });
+
+    scrollable(list).width(Length::Fill).height(Length::Fill).into()
+}
+
+fn session_status(session: &Session) -> String {
+    match &session.room {
+        Some(room) => format!(
+            "Connected - {}/{} checks",
+            room.checked_locations.len(),
+            room.checked_locations.len() + room.missing_locations.len()
+        ),
+        None => String::from("Connecting"),
     }
+}
+
+fn combined_view(context: &Context) -> Element<'_, Message> {
+    let rooms = context.sessions.values().fold(
+        Column::new().spacing(5).push(row![
+            text("Room").width(200),
+            text("Slot").width(150),
+            text("Server").width(250),
+            text("Status"),
+        ]),
+        |col, session| {
+            col.push(row![
+                text(session.label()).width(200),
+                text(&session.profile.connection_info.slot).width(150),
+                text(&session.profile.connection_info.server).width(250),
+                text(session_status(session)),
+            ])
+        },
+    );
+
+    column![
+        scrollable(rooms).height(Length::FillPortion(1)),
+        alert_list(context.alerts.iter().map(|(_, alert)| alert)),
+    ]
+    .spacing(context.appearance.density.space(20))
+    .into()
+}
+
+fn session_view<'a>(
+    dashboard: &'a Dashboard,
+    id: ConnectionId,
+    session: &'a Session,
+    context: &'a Context,
+) -> Element<'a, Message> {
+    let header = row![
+        text(format!("Slot: {} - Server: {}", session.profile.connection_info.slot, session.profile.connection_info.server))
+            .horizontal_alignment(iced::alignment::Horizontal::Right),
+        Space::with_width(100),
+    ]
+    .push_maybe(session.is_replay().then(|| button("Next frame").on_press(Message::StepReplay(id))))
+    .push(button("Close").on_press(Message::CloseSession(id)))
+    .spacing(10)
+    .align_items(Alignment::Center);
+
+    let sections = Section::ALL.iter().fold(Row::new().spacing(5), |row, section| {
+        row.push(tab(
+            section.to_string(),
+            dashboard.section == *section,
+            Message::DashboardSectionSelected(*section),
+        ))
+    });
 
-}
\ No newline at end of file
+    let alerts = context
+        .alerts
+        .iter()
+        .filter(move |(source, _)| *source == id)
+        .map(|(_, alert)| alert);
+    let highlighted = context.appearance.theme().palette().primary;
+    let line = |(print, flagged): (&PrintJSON, bool)| (session.render(print.data()), flagged);
+
+    let section: Element<Message> = match (&session.room, dashboard.section) {
+        (_, Section::Log) => message_log(
+            session.highlighted_log().map(line),
+            dashboard.highlight.and_then(|index| session.log_position(index)),
+            highlighted,
+        ),
+        (_, Section::Chat) => column![
+            message_log(session.chat().map(line), None, highlighted),
+            row![
+                text_input("Message", &dashboard.chat_input)
+                    .on_input(Message::ChatInputChanged)
+                    .on_submit(Message::SendChat),
+                button("Send").on_press_maybe(
+                    (session.room.is_some() && !dashboard.chat_input.is_empty()).then_some(Message::SendChat)
+                ),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10)
+        .into(),
+        (None, _) => text("Not connected").into(),
+        (Some(room), Section::Players) => scrollable(player_table(id, session, room)).into(),
+        (Some(_), Section::Hints) => hint_list(session),
+        (Some(room), Section::SlotData) => slot_data_inspector(room),
+    };
+
+    let content = column![
+        sections,
+        iced::widget::container(section).height(Length::FillPortion(2)),
+        iced::widget::container(alert_list(alerts)).height(Length::FillPortion(1)),
+    ]
+    .spacing(context.appearance.density.space(20));
+
+    column![header, content].spacing(context.appearance.density.space(20)).into()
+}
+
+pub fn view<'a>(dashboard: &'a Dashboard, context: &'a Context) -> Element<'a, Message> {
+    let tabs = context.sessions.iter().fold(
+        Row::new()
+            .spacing(5)
+            .push(tab("All".to_owned(), dashboard.selected.is_none(), Message::DashboardTabSelected(None))),
+        |tabs, (id, session)| {
+            tabs.push(tab(
+                session.label().to_owned(),
+                dashboard.selected == Some(*id),
+                Message::DashboardTabSelected(Some(*id)),
+            ))
+        },
+    )
+    .push(Space::with_width(Length::Fill))
+    .push(button("Add room").on_press(Message::Navigate(Route::Login)));
+
+    let content = match dashboard
+        .selected
+        .and_then(|id| context.sessions.get(&id).map(|session| (id, session)))
+    {
+        Some((id, session)) => session_view(dashboard, id, session, context),
+        None => combined_view(context),
+    };
+
+    iced::widget::container::Container::new(
+        column![tabs, content].spacing(context.appearance.density.space(20))
+    )
+    .padding(context.appearance.density.space(20))
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .into()
+}
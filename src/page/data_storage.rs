@@ -0,0 +1,63 @@
+use iced::widget::{button, column, row, scrollable, text, text_input, Column, Row};
+use iced::{Alignment, Element, Length};
+
+use crate::context::Context;
+use crate::update::{DataStorage, Message};
+
+use super::tab;
+
+/// Data storage keys watched by a session, with their current value.
+pub fn view<'a>(data_storage: &'a DataStorage, context: &'a Context) -> Element<'a, Message> {
+    let selected = data_storage.session(context);
+    let Some(session) = selected.and_then(|id| context.sessions.get(&id)) else {
+        return column![text("Data storage").size(24), text("Not connected")]
+            .spacing(20)
+            .into();
+    };
+
+    let tabs = context.sessions.iter().fold(Row::new().spacing(5), |tabs, (id, session)| {
+        tabs.push(tab(
+            session.label().to_owned(),
+            selected == Some(*id),
+            Message::DataStorageTabSelected(*id),
+        ))
+    });
+
+    // The read hints of the slot, a key every room has.
+    let placeholder = match &session.room {
+        Some(room) => format!("_read_hints_{}_{}", room.team, room.slot),
+        None => String::from("Key"),
+    };
+    let input = row![
+        text_input(&placeholder, &data_storage.key_input)
+            .on_input(Message::DataStorageKeyInputChanged)
+            .on_submit(Message::WatchKey),
+        button("Watch").on_press_maybe((!data_storage.key_input.trim().is_empty()).then_some(Message::WatchKey)),
+    ]
+    .spacing(10);
+
+    let keys = session.data_storage.iter().fold(Column::new().spacing(5), |col, (key, value)| {
+        let value = match value {
+            Some(value) => value.to_string(),
+            None => String::from("Waiting for the server"),
+        };
+        col.push(
+            row![
+                text(key).width(250),
+                text(value).width(Length::Fill),
+                button("Remove").on_press(Message::UnwatchKey(key.clone())),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        )
+    });
+
+    column![
+        text("Data storage").size(24),
+        tabs,
+        input,
+        scrollable(keys).width(Length::Fill).height(Length::Fill),
+    ]
+    .spacing(context.appearance.density.space(20))
+    .into()
+}
@@ -0,0 +1,28 @@
+use iced::widget::{column, scrollable, text, Column};
+use iced::{Element, Length};
+
+use crate::context::Context;
+use crate::update::Message;
+
+/// Hints of every session, the ones not found yet first.
+pub fn view(context: &Context) -> Element<'_, Message> {
+    let mut hints: Vec<_> = context
+        .sessions
+        .values()
+        .flat_map(|session| session.hints.iter().map(move |hint| (session, hint)))
+        .collect();
+    hints.sort_by_key(|(_, hint)| hint.found);
+
+    let content: Element<Message> = if hints.is_empty() {
+        text("No hints yet").into()
+    } else {
+        let list = hints.into_iter().fold(Column::new().spacing(5), |col, (session, hint)| {
+            col.push(text(format!("[{}] {}", session.label(), session.hint_text(hint))))
+        });
+        scrollable(list).width(Length::Fill).height(Length::Fill).into()
+    };
+
+    column![text("Hints").size(24), content]
+        .spacing(context.appearance.density.space(20))
+        .into()
+}
@@ -0,0 +1,61 @@
+use iced::widget::{button, column, container, mouse_area, row, text, Column, Space};
+use iced::{Alignment, Element, Length};
+
+use crate::overlay;
+use crate::update::{Message, State};
+
+/// The latest alerts still shown, newest first and fading out, under the
+/// countdown of any session counting down.
+pub fn view(state: &State) -> Element<'_, Message> {
+    let context = &state.context;
+    let density = context.appearance.density;
+    let color = context.appearance.theme().palette().text;
+
+    let countdown = context
+        .sessions
+        .values()
+        .filter_map(|session| session.countdown)
+        .filter(|countdown| overlay::countdown_visible(countdown, state.now))
+        .max_by_key(|countdown| countdown.at)
+        .map(|countdown| match countdown.remaining {
+            0 => text("GO").size(32),
+            remaining => text(remaining).size(32),
+        });
+
+    let alerts = context
+        .alerts
+        .iter()
+        .rev()
+        .map_while(|(_, alert)| {
+            let opacity = context.overlay.opacity(state.now.saturating_duration_since(alert.at))?;
+            Some(
+                text(format!("[{}] {}", alert.source, alert.text))
+                    .style(iced::theme::Text::Color(iced::Color { a: opacity, ..color })),
+            )
+        })
+        .take(context.overlay.alert_count())
+        .fold(Column::new().spacing(density.space(5)), Column::push);
+
+    let content = column![
+        row![
+            Space::with_width(Length::Fill),
+            button(text("Exit overlay").size(12))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::ToggleOverlay),
+        ]
+        .align_items(Alignment::Center),
+    ]
+    .push_maybe(countdown)
+    .push(alerts)
+    .spacing(density.space(5))
+    .align_items(Alignment::Center);
+
+    mouse_area(
+        container(content)
+            .padding(density.space(10))
+            .width(Length::Fill)
+            .height(Length::Fill),
+    )
+    .on_press(Message::DragOverlay)
+    .into()
+}
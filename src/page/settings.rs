@@ -0,0 +1,146 @@
+use iced::widget::{checkbox, column, container, pick_list, row, slider, text, text_input, Column};
+use iced::{Alignment, Element, Length};
+
+use crate::api;
+use crate::appearance::{self, Appearance, Density, PaletteColor, CUSTOM_THEME, TEXT_SCALES};
+use crate::context::Context;
+use crate::overlay::{ALERT_COUNTS, FADE_TIMEOUTS};
+use crate::secrets::PasswordStorage;
+use crate::throttle;
+use crate::update::Message;
+
+fn field<'a>(label: &str, input: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+    row![text(format!("{}: ", label)).width(150), input.into()]
+        .align_items(Alignment::Center)
+        .into()
+}
+
+/// A text input per palette color, with a swatch of the color it parses to.
+fn palette_editor(context: &Context) -> Element<'_, Message> {
+    let palette = &context.appearance.palette;
+    PaletteColor::ALL
+        .into_iter()
+        .fold(Column::new().spacing(5), |col, color| {
+            let swatch = match appearance::parse_color(palette.get(color)) {
+                Some(parsed) => container(text(""))
+                    .width(24)
+                    .height(24)
+                    .style(move |_: &iced::Theme| container::Appearance {
+                        background: Some(parsed.into()),
+                        ..Default::default()
+                    })
+                    .into(),
+                None => Element::from(
+                    text("Expected #rrggbb")
+                        .style(iced::theme::Text::Color(iced::Color::from_rgb(1.0, 0.33, 0.33))),
+                ),
+            };
+            col.push(field(
+                &color.to_string(),
+                row![
+                    text_input("#rrggbb", palette.get(color))
+                        .width(120)
+                        .on_input(move |input| Message::PaletteColorChanged(color, input)),
+                    swatch,
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            ))
+        })
+        .into()
+}
+
+/// Start and end of the quiet hours, with an error while either is not a
+/// time.
+fn quiet_hours(context: &Context) -> Element<'_, Message> {
+    let quiet_hours = &context.throttle_settings.quiet_hours;
+    let invalid = [&quiet_hours.start, &quiet_hours.end]
+        .into_iter()
+        .any(|time| !time.is_empty() && throttle::parse_time(time).is_none());
+    row![
+        text_input("22:00", &quiet_hours.start)
+            .width(80)
+            .on_input(Message::QuietHoursStartChanged),
+        text("to"),
+        text_input("08:00", &quiet_hours.end)
+            .width(80)
+            .on_input(Message::QuietHoursEndChanged),
+    ]
+    .push_maybe(invalid.then(|| {
+        text("Expected HH:MM").style(iced::theme::Text::Color(iced::Color::from_rgb(1.0, 0.33, 0.33)))
+    }))
+    .spacing(10)
+    .align_items(Alignment::Center)
+    .into()
+}
+
+pub fn view(context: &Context) -> Element<'_, Message> {
+    let appearance = &context.appearance;
+    let density = appearance.density;
+
+    let editor = (appearance.theme == CUSTOM_THEME).then(|| palette_editor(context));
+
+    column![
+        text("Settings").size(24),
+        column![
+            field(
+                "Theme",
+                pick_list(Appearance::theme_names(), Some(appearance.theme.clone()), Message::ThemeSelected),
+            ),
+        ]
+        .push_maybe(editor)
+        .push(field(
+            "Text scale",
+            row![
+                slider(TEXT_SCALES, appearance.text_scale(), Message::TextScaleChanged)
+                    .step(0.05)
+                    .width(200)
+                    .on_release(Message::TextScaleReleased),
+                text(format!("{:.0}%", appearance.text_scale() * 100.0)),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        ))
+        .push(field(
+            "Density",
+            pick_list(&Density::ALL[..], Some(density), Message::DensitySelected),
+        ))
+        .push(field(
+            "Overlay alerts",
+            row![
+                slider(ALERT_COUNTS, context.overlay.alert_count, Message::OverlayAlertCountChanged)
+                    .width(200)
+                    .on_release(Message::OverlaySettingsReleased),
+                text(context.overlay.alert_count()),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        ))
+        .push(field(
+            "Overlay fade out",
+            row![
+                slider(FADE_TIMEOUTS, context.overlay.fade_timeout, Message::OverlayFadeTimeoutChanged)
+                    .width(200)
+                    .on_release(Message::OverlaySettingsReleased),
+                text(format!("{} s", context.overlay.fade_timeout)),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        ))
+        .push(field(
+            "Do not disturb",
+            checkbox("Hold alerts until turned off", context.throttle_settings.do_not_disturb)
+                .on_toggle(Message::DoNotDisturbToggled),
+        ))
+        .push(field("Quiet hours", quiet_hours(context)))
+        .push(field(
+            "Password storage",
+            pick_list(&PasswordStorage::ALL[..], Some(context.password_storage), Message::PasswordStorageSelected),
+        ))
+        .push(field("Local API", text(api::describe(context.api.as_ref()))))
+        .spacing(density.space(10))
+        .width(Length::Fill),
+    ]
+    .spacing(density.space(20))
+    .into()
+}
@@ -0,0 +1,85 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alert::AlertRules;
+use crate::ap::connection::ConnectionInfo;
+use crate::mention::Mentions;
+use crate::secrets::new_secret_id;
+use crate::watchlist::Watch;
+
+/// A named set of connection settings and alert rules for one slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Stable key of the profile's password in the secret store.
+    #[serde(default = "new_secret_id")]
+    pub id: String,
+    pub name: String,
+    pub connection_info: ConnectionInfo,
+    #[serde(default)]
+    pub alert_rules: AlertRules,
+    /// Items raising a [`AlertKind::Watched`](crate::alert::AlertKind::Watched) alert.
+    #[serde(default)]
+    pub watchlist: Vec<Watch>,
+    #[serde(default)]
+    pub mentions: Mentions,
+    /// Unix timestamp in seconds of the last connection attempt.
+    #[serde(default)]
+    pub last_used: Option<u64>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            id: new_secret_id(),
+            name: String::from("Default"),
+            connection_info: Default::default(),
+            alert_rules: Default::default(),
+            watchlist: Vec::new(),
+            mentions: Default::default(),
+            last_used: None,
+        }
+    }
+}
+
+impl Profile {
+    pub fn touch(&mut self) {
+        self.last_used = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_secs());
+    }
+}
+
+/// Entry of the profile picker, compared by position so that duplicated
+/// names can still be told apart.
+#[derive(Debug, Clone)]
+pub struct ProfileChoice {
+    pub index: usize,
+    pub name: String,
+}
+
+impl PartialEq for ProfileChoice {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl std::fmt::Display for ProfileChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Returns `base`, or `base` followed by the first free number, so that it
+/// does not clash with an existing profile name.
+pub fn unique_name(profiles: &[Profile], base: &str) -> String {
+    let taken = |name: &str| profiles.iter().any(|profile| profile.name == name);
+    if !taken(base) {
+        return base.to_owned();
+    }
+    (2..)
+        .map(|n| format!("{} {}", base, n))
+        .find(|name| !taken(name))
+        .unwrap_or_default()
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context as _};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Where slot passwords are kept between runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PasswordStorage {
+    #[default]
+    Encrypted,
+    Never,
+}
+
+impl PasswordStorage {
+    pub const ALL: [PasswordStorage; 2] = [PasswordStorage::Encrypted, PasswordStorage::Never];
+}
+
+impl std::fmt::Display for PasswordStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasswordStorage::Encrypted => f.write_str("Save encrypted"),
+            PasswordStorage::Never => f.write_str("Never save"),
+        }
+    }
+}
+
+/// On disk layout of the secrets file. The ciphertext holds the JSON map of
+/// secrets, encrypted with a key derived from the user's passphrase.
+#[derive(Serialize, Deserialize)]
+struct SecretFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Passwords encrypted at rest in a file next to the config, so that no
+/// keyring daemon is needed. Locked until [`SecretStore::unlock`] is called.
+#[derive(Default)]
+pub struct SecretStore {
+    path: PathBuf,
+    salt: [u8; SALT_LENGTH],
+    key: Option<Key>,
+    secrets: BTreeMap<String, String>,
+}
+
+impl std::fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretStore")
+            .field("path", &self.path)
+            .field("unlocked", &self.is_unlocked())
+            .finish_non_exhaustive()
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> anyhow::Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow!("could not derive key: {}", err))?;
+    Ok(key)
+}
+
+impl SecretStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Decrypts the secrets file, or sets the passphrase of a new one.
+    pub fn unlock(&mut self, passphrase: &str) -> anyhow::Result<()> {
+        if passphrase.is_empty() {
+            return Err(anyhow!("passphrase is empty"));
+        }
+
+        if !self.exists() {
+            OsRng.fill_bytes(&mut self.salt);
+            self.key = Some(derive_key(passphrase, &self.salt)?);
+            self.secrets.clear();
+            return Ok(());
+        }
+
+        let file = std::fs::File::open(&self.path).context("could not open secrets file")?;
+        let file: SecretFile = serde_json::from_reader(file).context("could not parse secrets file")?;
+
+        let salt = BASE64.decode(file.salt)?;
+        let nonce = BASE64.decode(file.nonce)?;
+        let ciphertext = BASE64.decode(file.ciphertext)?;
+        if salt.len() != SALT_LENGTH || nonce.len() != NONCE_LENGTH {
+            return Err(anyhow!("secrets file is corrupted"));
+        }
+
+        let key = derive_key(passphrase, &salt)?;
+        let plaintext = ChaCha20Poly1305::new(&key)
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| anyhow!("wrong passphrase"))?;
+
+        self.secrets = serde_json::from_slice(&plaintext)?;
+        self.salt.copy_from_slice(&salt);
+        self.key = Some(key);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.secrets.get(id).map(String::as_str)
+    }
+
+    /// Stores a secret, an empty one removes the entry.
+    pub fn set(&mut self, id: &str, secret: &str) {
+        if secret.is_empty() {
+            self.secrets.remove(id);
+        } else {
+            self.secrets.insert(id.to_owned(), secret.to_owned());
+        }
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.secrets.retain(|id, _| keep(id));
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let key = self.key.as_ref().ok_or_else(|| anyhow!("secrets are locked"))?;
+
+        let mut nonce = [0u8; NONCE_LENGTH];
+        OsRng.fill_bytes(&mut nonce);
+
+        let plaintext = serde_json::to_vec(&self.secrets)?;
+        let ciphertext = ChaCha20Poly1305::new(key)
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| anyhow!("could not encrypt secrets"))?;
+
+        let file = SecretFile {
+            version: 1,
+            salt: BASE64.encode(self.salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        config::write_atomic(&self.path, &serde_json::to_vec(&file)?)
+    }
+
+    /// Forgets every secret and removes the file.
+    pub fn delete(&mut self) -> anyhow::Result<()> {
+        self.secrets.clear();
+        self.key = None;
+        if self.exists() {
+            std::fs::remove_file(&self.path).context("could not remove secrets file")?;
+        }
+        Ok(())
+    }
+}
+
+/// Random identifier used to key a profile's secrets.
+pub fn new_secret_id() -> String {
+    format!("{:016x}", OsRng.next_u64())
+}
@@ -0,0 +1,597 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::alert::{Alert, AlertKind};
+use crate::inbox::Inbox;
+use crate::metrics::Stats;
+use crate::ap::connection::{Connection, Event, InputMessage, Source};
+use crate::ap::messages::{
+    APClientMessage, APServerMessage, Connected, Get, GetDataPackage, JSONMessagePart, NetworkItem,
+    PrintJSON, Say, SetNotify,
+};
+use crate::profile::Profile;
+use crate::watchlist::{Watch, Watchlist};
+use ap_client::names::Names;
+
+/// Messages kept in the log of each session, older ones are dropped.
+const LOG_LIMIT: usize = 1000;
+
+/// `ClientStatus` of a slot with a client connected, before it is ready.
+pub const CLIENT_CONNECTED: u64 = 5;
+/// `ClientStatus` of a slot that completed its goal.
+pub const CLIENT_GOAL: u64 = 30;
+
+/// Data storage key holding the `ClientStatus` of a slot.
+fn client_status_key(team: u32, slot: u32) -> String {
+    format!("_read_client_status_{}_{}", team, slot)
+}
+
+/// Slot whose `ClientStatus` is held by `key`, if it is one of team `team`.
+fn client_status_slot(team: u32, key: &str) -> Option<u32> {
+    key.strip_prefix(&format!("_read_client_status_{}_", team))?.parse().ok()
+}
+
+/// A hint involving one of the slot's items or locations.
+#[derive(Debug, Clone)]
+pub struct Hint {
+    pub receiving: u32,
+    pub item: NetworkItem,
+    pub found: bool,
+}
+
+/// Last number of a `!countdown` started in the room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Countdown {
+    /// Seconds left, 0 once it is over.
+    pub remaining: u32,
+    pub at: Instant,
+}
+
+/// Part of a session shown below its header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Section {
+    #[default]
+    Players,
+    Log,
+    Hints,
+    Chat,
+    SlotData,
+}
+
+impl Section {
+    pub const ALL: [Section; 5] = [
+        Section::Players,
+        Section::Log,
+        Section::Hints,
+        Section::Chat,
+        Section::SlotData,
+    ];
+}
+
+impl std::fmt::Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Section::Players => "Players",
+            Section::Log => "Log",
+            Section::Hints => "Hints",
+            Section::Chat => "Chat",
+            Section::SlotData => "Slot data",
+        })
+    }
+}
+
+/// One live connection to a room, started from a profile.
+#[derive(Debug)]
+pub struct Session {
+    pub profile: Profile,
+    pub source: Source,
+    pub worker_channel: Option<Connection>,
+    pub room: Option<Connected>,
+    pub names: Names,
+    /// Every `PrintJSON` received, oldest first.
+    pub log: VecDeque<PrintJSON>,
+    /// Messages dropped from the front of the log.
+    pub log_dropped: u64,
+    /// Whether each message of the log is highlighted, see
+    /// [`Session::highlighted_log`].
+    highlights: VecDeque<bool>,
+    /// Loaded by [`Context::process`](crate::context::Context::process) once
+    /// the slot connects.
+    pub inbox: Inbox,
+    pub hints: Vec<Hint>,
+    /// Compiled from the profile's watchlist.
+    pub watchlist: Watchlist,
+    /// Messages for the worker, delivered by [`Context::flush`] so that
+    /// updating a session never does any I/O.
+    ///
+    /// [`Context::flush`]: crate::context::Context::flush
+    pub outbox: Vec<InputMessage>,
+    /// Watched data storage keys, with their value once retrieved.
+    pub data_storage: BTreeMap<String, Option<serde_json::Value>>,
+    /// Set once asked to disconnect, the session is dropped when the worker
+    /// is done.
+    pub closing: bool,
+    pub countdown: Option<Countdown>,
+    pub stats: Stats,
+    /// Seed of the room, from its `RoomInfo`.
+    pub seed: Option<String>,
+    /// Whether the slot completed its goal.
+    pub goal: bool,
+}
+
+impl Session {
+    pub fn new(profile: Profile, source: Source) -> Self {
+        Self {
+            watchlist: Watchlist::new(&profile.watchlist),
+            profile,
+            source,
+            worker_channel: None,
+            room: None,
+            names: Names::default(),
+            log: VecDeque::new(),
+            log_dropped: 0,
+            highlights: VecDeque::new(),
+            inbox: Inbox::default(),
+            hints: Vec::new(),
+            outbox: Vec::new(),
+            data_storage: BTreeMap::new(),
+            closing: false,
+            countdown: None,
+            stats: Stats::default(),
+            seed: None,
+            goal: false,
+        }
+    }
+
+    /// Replaces the profile of a running session, along with what is built
+    /// from it.
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.watchlist = Watchlist::new(&profile.watchlist);
+        self.profile = profile;
+        self.refresh_highlights();
+    }
+
+    /// Replaces the watchlist of the profile, matching it from now on.
+    pub fn set_watchlist(&mut self, watches: Vec<Watch>) {
+        self.watchlist = Watchlist::new(&watches);
+        self.profile.watchlist = watches;
+        self.refresh_highlights();
+    }
+
+    /// Mutes or unmutes a player going by any of `names`.
+    pub fn mute(&mut self, names: &[&str], muted: bool) {
+        self.profile.mentions.set_muted(names, muted);
+        self.refresh_highlights();
+    }
+
+    /// Name shown next to everything coming from this session.
+    pub fn label(&self) -> &str {
+        &self.profile.name
+    }
+
+    pub fn connect(&mut self) {
+        let request = self.profile.connection_info.to_request();
+        self.outbox.push(InputMessage::Connect(request));
+    }
+
+    pub fn disconnect(&mut self) {
+        self.outbox.push(InputMessage::Disconnect);
+    }
+
+    /// Disconnects for good. Returns whether the worker has to be waited
+    /// for, one that is not ready yet has no socket to close.
+    pub fn close(&mut self) -> bool {
+        self.closing = true;
+        if self.worker_channel.is_none() {
+            return false;
+        }
+        self.disconnect();
+        true
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self.source, Source::Replay { .. })
+    }
+
+    /// Plays the next frame of a stepped replay.
+    pub fn step(&mut self) {
+        self.outbox.push(InputMessage::Step);
+    }
+
+    pub fn send(&mut self, messages: Vec<APClientMessage>) {
+        self.outbox.push(InputMessage::Send(messages));
+    }
+
+    pub fn say(&mut self, text: String) {
+        self.send(vec![APClientMessage::Say(Say { text })]);
+    }
+
+    /// Fetches a data storage key and follows its changes.
+    pub fn watch(&mut self, key: String) {
+        if self.data_storage.contains_key(&key) {
+            return;
+        }
+        self.data_storage.insert(key.clone(), None);
+        if self.room.is_some() {
+            self.request_data_storage(vec![key]);
+        }
+    }
+
+    pub fn unwatch(&mut self, key: &str) {
+        // The server has no way to stop notifications, they are ignored.
+        self.data_storage.remove(key);
+    }
+
+    /// Position in [`Session::log`] of the message at `index` counting the
+    /// dropped ones, if it is still kept.
+    pub fn log_position(&self, index: u64) -> Option<usize> {
+        let position = index.checked_sub(self.log_dropped)? as usize;
+        (position < self.log.len()).then_some(position)
+    }
+
+    /// Messages of the log, with whether they name a watched item or
+    /// mention the slot.
+    pub fn highlighted_log(&self) -> impl DoubleEndedIterator<Item = (&PrintJSON, bool)> {
+        self.log.iter().zip(self.highlights.iter().copied())
+    }
+
+    /// Chat of the room, without the messages of muted players, like
+    /// [`Session::highlighted_log`].
+    pub fn chat(&self) -> impl DoubleEndedIterator<Item = (&PrintJSON, bool)> {
+        self.highlighted_log().filter(|(print, _)| match print {
+            PrintJSON::Chat { slot, .. } => !self.is_muted(*slot),
+            PrintJSON::ServerChat { .. } => true,
+            _ => false,
+        })
+    }
+
+    /// Whether the player's slot name or alias is on the profile's mute list.
+    pub fn is_muted(&self, slot: u32) -> bool {
+        let name = self
+            .room
+            .as_ref()
+            .and_then(|room| room.slot_info.get(&slot))
+            .map(|info| info.name.as_str());
+        self.profile
+            .mentions
+            .is_muted(name.into_iter().chain(self.player_name(slot)))
+    }
+
+    /// Whether a chat message from someone else, not muted, names the slot
+    /// or a keyword of the profile.
+    pub fn is_mention(&self, print: &PrintJSON) -> bool {
+        let Some(room) = &self.room else {
+            return false;
+        };
+        let message = match print {
+            PrintJSON::Chat { team, slot, message, .. }
+                if (*team, *slot) != (room.team, room.slot) && !self.is_muted(*slot) =>
+            {
+                message
+            }
+            PrintJSON::ServerChat { message, .. } => message,
+            _ => return false,
+        };
+        let names = [self.profile.connection_info.slot.as_str()].into_iter().chain(self.player_name(room.slot));
+        self.profile.mentions.is_mention(message, names)
+    }
+
+    /// Whether the message is drawn highlighted, naming a watched item or
+    /// mentioning the slot.
+    fn is_highlighted(&self, print: &PrintJSON) -> bool {
+        self.mentions_watched(print) || self.is_mention(print)
+    }
+
+    /// Highlights the log again once the names, the room or the profile
+    /// changed. Flags are kept rather than matched again on every view.
+    fn refresh_highlights(&mut self) {
+        self.highlights = self.log.iter().map(|print| self.is_highlighted(print)).collect();
+    }
+
+    /// Alias of a player of the slot's team.
+    pub fn player_name(&self, slot: u32) -> Option<&str> {
+        let room = self.room.as_ref()?;
+        room.players
+            .iter()
+            .find(|player| player.team == room.team && player.slot == slot)
+            .map(|player| player.alias.as_str())
+    }
+
+    pub fn game(&self, slot: u32) -> Option<&str> {
+        self.room.as_ref()?.slot_info.get(&slot).map(|info| info.game.as_str())
+    }
+
+    pub fn item_name(&self, slot: u32, item: i64) -> Option<&str> {
+        self.names.item(self.game(slot)?, item)
+    }
+
+    pub fn location_name(&self, slot: u32, location: i64) -> Option<&str> {
+        self.names.location(self.game(slot)?, location)
+    }
+
+    /// Whether the item of `slot`'s game is on the watchlist.
+    pub fn is_watched(&self, slot: u32, item: i64) -> bool {
+        self.game(slot).is_some_and(|game| {
+            self.names
+                .item(game, item)
+                .is_some_and(|name| self.watchlist.contains(game, name))
+        })
+    }
+
+    /// Whether the message names an item of the watchlist, for whoever it is.
+    fn mentions_watched(&self, print: &PrintJSON) -> bool {
+        print.data().iter().any(|part| {
+            part.r#type.as_deref() == Some("item_id")
+                && part
+                    .text
+                    .as_deref()
+                    .and_then(|text| text.parse().ok())
+                    .zip(part.player)
+                    .is_some_and(|(item, slot)| self.is_watched(slot, item))
+        })
+    }
+
+    /// Whether the message sends a watched item to the slot, finds one at a
+    /// location of the slot's world, or hints at one either way, found or
+    /// not.
+    fn is_watched_for_slot(&self, room: &Connected, print: &PrintJSON) -> bool {
+        match print {
+            PrintJSON::ItemSend { receiving, item, .. } | PrintJSON::Hint { receiving, item, .. } => {
+                (*receiving == room.slot || item.player == room.slot) && self.is_watched(*receiving, item.item)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn hint_text(&self, hint: &Hint) -> String {
+        let player = |slot| {
+            self.player_name(slot)
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("Player {}", slot))
+        };
+        let item = self
+            .item_name(hint.receiving, hint.item.item)
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("Item {}", hint.item.item));
+        let location = self
+            .location_name(hint.item.player, hint.item.location)
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("Location {}", hint.item.location));
+
+        format!(
+            "{}'s {} is at {} in {}'s world{}",
+            player(hint.receiving),
+            item,
+            location,
+            player(hint.item.player),
+            if hint.found { " (found)" } else { "" }
+        )
+    }
+
+    /// Turns the parts of a `PrintJSON` into plain text, resolving players,
+    /// items and locations.
+    pub fn render(&self, parts: &[JSONMessagePart]) -> String {
+        parts.iter().map(|part| self.render_part(part)).collect()
+    }
+
+    /// Text of a message part, with player, item and location ids replaced
+    /// by their names when known.
+    pub fn render_part(&self, part: &JSONMessagePart) -> String {
+        let text = part.text.clone().unwrap_or_default();
+        let name = match part.r#type.as_deref() {
+            Some("player_id") => text
+                .parse()
+                .ok()
+                .and_then(|slot| self.player_name(slot)),
+            Some("item_id") => text
+                .parse()
+                .ok()
+                .zip(part.player)
+                .and_then(|(item, slot)| self.item_name(slot, item)),
+            Some("location_id") => text
+                .parse()
+                .ok()
+                .zip(part.player)
+                .and_then(|(location, slot)| self.location_name(slot, location)),
+            _ => None,
+        };
+        name.map(str::to_owned).unwrap_or(text)
+    }
+
+    /// Updates the room state, returning the alert raised by the event if any.
+    pub fn process(&mut self, event: &Event) -> Option<Alert> {
+        match event {
+            Event::WorkerReady(worker) => {
+                self.worker_channel.replace(worker.clone());
+                self.connect();
+                None
+            }
+            Event::APMessage(APServerMessage::RoomInfo(room_info)) => {
+                self.seed.replace(room_info.seed_name.clone());
+                None
+            }
+            Event::APMessage(APServerMessage::Connected(connected)) => {
+                let status_keys: Vec<String> = connected
+                    .players
+                    .iter()
+                    .filter(|player| player.team == connected.team)
+                    .map(|player| client_status_key(player.team, player.slot))
+                    .collect();
+                self.room.replace(connected.clone());
+                self.stats.connections += 1;
+                self.request_data_package();
+                let keys = self.data_storage.keys().cloned().chain(status_keys).collect();
+                self.request_data_storage(keys);
+                self.refresh_highlights();
+                None
+            }
+            Event::APMessage(APServerMessage::RoomUpdate(update)) => {
+                if let Some(room) = &mut self.room {
+                    if let Some(hint_points) = update.hint_points {
+                        room.hint_points = hint_points;
+                    }
+                    if let Some(players) = &update.players {
+                        room.players.clone_from(players);
+                    }
+                    for location in update.checked_locations.iter().flatten() {
+                        if !room.checked_locations.contains(location) {
+                            room.checked_locations.push(*location);
+                        }
+                        room.missing_locations.retain(|missing| missing != location);
+                    }
+                }
+                if update.players.is_some() {
+                    self.refresh_highlights();
+                }
+                None
+            }
+            Event::APMessage(APServerMessage::Retrieved(retrieved)) => {
+                for (key, value) in &retrieved.keys {
+                    self.record_data_storage(key, value);
+                }
+                None
+            }
+            Event::APMessage(APServerMessage::SetReply(reply)) => {
+                self.record_data_storage(&reply.key, &reply.value);
+                None
+            }
+            Event::APMessage(APServerMessage::DataPackage(data_package)) => {
+                self.names.add(&data_package.data.games);
+                self.refresh_highlights();
+                None
+            }
+            Event::APMessage(APServerMessage::PrintJSON(print)) => {
+                match print {
+                    PrintJSON::Goal { team, slot, .. }
+                        if self.room.as_ref().is_some_and(|room| room.team == *team && room.slot == *slot) =>
+                    {
+                        self.goal = true;
+                    }
+                    PrintJSON::Hint {
+                        receiving, item, found, ..
+                    } => self.record_hint(*receiving, item, *found),
+                    PrintJSON::Countdown { countdown, .. } => {
+                        self.countdown.replace(Countdown {
+                            remaining: *countdown,
+                            at: Instant::now(),
+                        });
+                    }
+                    _ => {}
+                }
+                if self.room.is_some() {
+                    self.stats.record(print);
+                }
+                self.highlights.push_back(self.is_highlighted(print));
+                self.log.push_back(print.clone());
+                if self.log.len() > LOG_LIMIT {
+                    self.log.pop_front();
+                    self.highlights.pop_front();
+                    self.log_dropped += 1;
+                }
+
+                let room = self.room.as_ref()?;
+                let rules = &self.profile.alert_rules;
+                let kind = if rules.watched && self.is_watched_for_slot(room, print) {
+                    AlertKind::Watched
+                } else if rules.mention && self.is_mention(print) {
+                    AlertKind::Mention
+                } else {
+                    rules.evaluate(room, print)?
+                };
+                let alert = Alert {
+                    kind,
+                    source: self.label().to_owned(),
+                    text: self.render(print.data()),
+                    at: Instant::now(),
+                    log_index: Some(self.log_dropped + self.log.len() as u64 - 1),
+                };
+                info!("Alert from {}: {}", alert.source, alert.text);
+                Some(alert)
+            }
+            Event::APMessage(_) => None,
+            Event::ParseFailed => {
+                self.stats.parse_failures += 1;
+                None
+            }
+            Event::Disconnected => {
+                self.room = None;
+                self.stats.client_statuses.clear();
+                None
+            }
+        }
+    }
+
+    fn request_data_package(&mut self) {
+        let Some(room) = &self.room else {
+            return;
+        };
+
+        let games = if room.slot_info.is_empty() {
+            None
+        } else {
+            let mut games: Vec<String> = room
+                .slot_info
+                .values()
+                .map(|info| info.game.clone())
+                .filter(|game| !self.names.has_game(game))
+                .collect();
+            games.sort();
+            games.dedup();
+            if games.is_empty() {
+                return;
+            }
+            Some(games)
+        };
+
+        self.send(vec![APClientMessage::GetDataPackage(GetDataPackage { games })]);
+    }
+
+    fn request_data_storage(&mut self, keys: Vec<String>) {
+        self.send(vec![
+            APClientMessage::Get(Get { keys: keys.clone() }),
+            APClientMessage::SetNotify(SetNotify { keys }),
+        ]);
+    }
+
+    /// Updates a watched key, or the client status of a slot of the team
+    /// and whether the slot completed its goal.
+    fn record_data_storage(&mut self, key: &str, value: &serde_json::Value) {
+        if let Some(room) = &self.room {
+            if let Some(slot) = client_status_slot(room.team, key) {
+                let status = value.as_u64().unwrap_or_default();
+                self.stats.client_statuses.insert(slot, status);
+                if slot == room.slot {
+                    self.goal = status == CLIENT_GOAL;
+                }
+            }
+        }
+        if let Some(watched) = self.data_storage.get_mut(key) {
+            watched.replace(value.clone());
+        }
+    }
+
+    fn record_hint(&mut self, receiving: u32, item: &NetworkItem, found: bool) {
+        let Some(room) = &self.room else {
+            return;
+        };
+        if receiving != room.slot && item.player != room.slot {
+            return;
+        }
+
+        let known = self
+            .hints
+            .iter_mut()
+            .find(|hint| hint.item.player == item.player && hint.item.location == item.location);
+        match known {
+            Some(hint) => hint.found = found,
+            None => self.hints.push(Hint {
+                receiving,
+                item: item.clone(),
+                found,
+            }),
+        }
+    }
+}
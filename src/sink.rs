@@ -0,0 +1,92 @@
+//! Places alerts and room events are sent to besides the front ends, set up
+//! in the `sinks` part of the config. They get the same [`api::Event`]s as
+//! the local API.
+
+pub mod mqtt;
+pub mod smtp;
+
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api;
+use crate::session::Session;
+use crate::throttle::{RateLimit, RateLimits};
+
+/// Keys of the sink passwords in the secret store.
+pub const SECRET_IDS: [&str; 2] = ["sink.mqtt", "sink.smtp"];
+
+/// Sinks enabled in the config, `None` being disabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mqtt: Option<mqtt::Settings>,
+    pub smtp: Option<smtp::Settings>,
+}
+
+impl Settings {
+    /// Passwords of the enabled sinks, with their key in the secret store.
+    /// Like slot passwords, they are only written to the config until the
+    /// secret store is unlocked.
+    pub fn passwords_mut(&mut self) -> impl Iterator<Item = (&'static str, &mut Option<String>)> {
+        let mqtt = self.mqtt.as_mut().map(|mqtt| &mut mqtt.password);
+        let smtp = self.smtp.as_mut().map(|smtp| &mut smtp.password);
+        SECRET_IDS
+            .into_iter()
+            .zip([mqtt, smtp])
+            .filter_map(|(id, password)| Some((id, password?)))
+    }
+
+    pub fn passwords(&self) -> [(&'static str, Option<&str>); 2] {
+        let mqtt = self.mqtt.as_ref().and_then(|mqtt| mqtt.password.as_deref());
+        let smtp = self.smtp.as_ref().and_then(|smtp| smtp.password.as_deref());
+        [(SECRET_IDS[0], mqtt), (SECRET_IDS[1], smtp)]
+    }
+
+    pub fn has_passwords(&self) -> bool {
+        self.passwords().iter().any(|(_, password)| password.is_some())
+    }
+}
+
+/// The running sinks.
+#[derive(Debug, Default)]
+pub struct Sinks {
+    mqtt: Option<(mqtt::Mqtt, RateLimit)>,
+    smtp: Option<smtp::Smtp>,
+}
+
+impl Sinks {
+    pub fn start(settings: &Settings, limits: &RateLimits) -> Self {
+        Self {
+            mqtt: settings.mqtt.clone().map(|mqtt| (mqtt::Mqtt::start(mqtt), RateLimit::new(limits.mqtt))),
+            smtp: settings.smtp.clone().map(smtp::Smtp::start),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mqtt.is_none() && self.smtp.is_none()
+    }
+
+    /// Sends an event of `session`, alerts within the rate limit of each
+    /// sink unless urgent.
+    pub fn publish(&mut self, session: &Session, event: &api::Event, now: Instant) {
+        if let Some((mqtt, limit)) = &mut self.mqtt {
+            if !event.is_rate_limited() || limit.allow(now) {
+                mqtt.publish_event(session, event);
+            }
+        }
+        if let Some(smtp) = &self.smtp {
+            smtp.publish_event(event);
+        }
+    }
+
+    /// Sends the state of `session`, sinks skip what did not change.
+    pub fn update(&self, session: &Session) {
+        if let Some((mqtt, _)) = &self.mqtt {
+            mqtt.publish_state(session);
+        }
+        if let Some(smtp) = &self.smtp {
+            smtp.publish_state(session);
+        }
+    }
+}
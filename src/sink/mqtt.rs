@@ -0,0 +1,382 @@
+//! Publishes alerts, room events and the state of the slot to an MQTT broker,
+//! for home automation.
+//!
+//! Only what publishing needs of MQTT 3.1.1 is spoken: messages are sent with
+//! QoS 0, and state topics are retained so that subscribers get the current
+//! value as soon as they subscribe. Try it against a local broker with, for
+//! instance, `mosquitto -v` and `mosquitto_sub -t 'ap/#' -v`.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{bail, Context as _};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::api;
+use crate::session::Session;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const RETAIN: u8 = 0x01;
+const PINGREQ: [u8; 2] = [0xc0, 0x00];
+const DISCONNECT: [u8; 2] = [0xe0, 0x00];
+
+/// Topics are templates, in which `{seed}` is the seed of the room, `{slot}`
+/// the slot name, `{kind}` the kind of alert or `PrintJSON` message and
+/// `{name}` the name of a state value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    /// Kept in the secret store once it is unlocked, see
+    /// [`sink::Settings::passwords`](crate::sink::Settings::passwords).
+    /// Only sent along with a username, as MQTT 3.1.1 requires.
+    pub password: Option<String>,
+    /// Alerts, `{kind}` being for instance `item_received`.
+    pub alert_topic: String,
+    /// Every `PrintJSON` message, `{kind}` being for instance `ItemSend`.
+    pub event_topic: String,
+    /// Retained `checks_remaining` and `goal` of the slot.
+    pub state_topic: String,
+    /// Seconds between pings.
+    pub keep_alive: u16,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            host: String::from("localhost"),
+            port: 1883,
+            client_id: String::from("ap_alert"),
+            username: None,
+            password: None,
+            alert_topic: String::from("ap/{seed}/{slot}/{kind}"),
+            event_topic: String::from("ap/{seed}/{slot}/events/{kind}"),
+            state_topic: String::from("ap/{seed}/{slot}/{name}"),
+            keep_alive: 60,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Message {
+    topic: String,
+    payload: Vec<u8>,
+    retain: bool,
+}
+
+/// Handle on the client, which runs on its own thread and reconnects on its
+/// own until the app exits.
+#[derive(Debug)]
+pub struct Mqtt {
+    settings: Settings,
+    messages: mpsc::UnboundedSender<Message>,
+}
+
+/// Fills a topic template, replacing what would make a value span several
+/// levels or read as a wildcard.
+fn topic(template: &str, session: &Session, kind: &str) -> String {
+    let level = |value: &str| value.replace(['/', '+', '#'], "_");
+    template
+        .replace("{seed}", &level(session.seed.as_deref().unwrap_or("unknown")))
+        .replace("{slot}", &level(&session.profile.connection_info.slot))
+        .replace("{kind}", &level(kind))
+        .replace("{name}", &level(kind))
+}
+
+impl Mqtt {
+    pub fn start(settings: Settings) -> Self {
+        if settings.password.is_some() && settings.username.is_none() {
+            warn!("The MQTT password is not sent without a username");
+        }
+        let (messages, receiver) = mpsc::unbounded_channel();
+        let client_settings = settings.clone();
+        let spawned = std::thread::Builder::new().name(String::from("mqtt")).spawn(move || {
+            match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime.block_on(run(client_settings, receiver)),
+                Err(err) => error!("MQTT sink stopped: {}", err),
+            }
+        });
+        if let Err(err) = spawned {
+            error!("Could not start the MQTT sink: {}", err);
+        }
+        Self { settings, messages }
+    }
+
+    fn send(&self, topic: String, payload: Vec<u8>, retain: bool) {
+        let _ = self.messages.send(Message { topic, payload, retain });
+    }
+
+    pub fn publish_event(&self, session: &Session, event: &api::Event) {
+        let (template, kind) = match event {
+            api::Event::Alert { kind, .. } => (
+                &self.settings.alert_topic,
+                serde_json::to_value(kind).ok().and_then(|kind| kind.as_str().map(str::to_owned)),
+            ),
+            api::Event::Print { kind, .. } => (&self.settings.event_topic, Some((*kind).to_owned())),
+        };
+        match serde_json::to_vec(event) {
+            Ok(payload) => self.send(topic(template, session, &kind.unwrap_or_default()), payload, false),
+            Err(err) => error!("Could not serialize {:?}: {}", event, err),
+        }
+    }
+
+    pub fn publish_state(&self, session: &Session) {
+        let Some(room) = &session.room else {
+            return;
+        };
+        let state = [
+            ("checks_remaining", room.missing_locations.len().to_string()),
+            ("goal", session.goal.to_string()),
+        ];
+        for (name, value) in state {
+            self.send(topic(&self.settings.state_topic, session, name), value.into_bytes(), true);
+        }
+    }
+}
+
+/// Connects and publishes until the app exits, keeping the last value of
+/// every retained topic to send it again after a reconnect.
+async fn run(settings: Settings, mut messages: mpsc::UnboundedReceiver<Message>) {
+    let mut retained: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    loop {
+        match connect(&settings).await {
+            Ok(stream) => {
+                info!("Connected to the MQTT broker at {}:{}", settings.host, settings.port);
+                match publish(stream, &settings, &mut retained, &mut messages).await {
+                    Ok(()) => return,
+                    Err(err) => warn!("Lost the MQTT broker: {:#}", err),
+                }
+            }
+            Err(err) => error!("Could not connect to the MQTT broker at {}:{}: {:#}", settings.host, settings.port, err),
+        }
+
+        // Events are dropped while disconnected, the state is kept.
+        let delay = tokio::time::sleep(RECONNECT_DELAY);
+        tokio::pin!(delay);
+        loop {
+            tokio::select! {
+                _ = &mut delay => break,
+                message = messages.recv() => match message {
+                    Some(message) if message.retain => {
+                        retained.insert(message.topic, message.payload);
+                    }
+                    Some(_) => {}
+                    None => return,
+                },
+            }
+        }
+    }
+}
+
+async fn connect(settings: &Settings) -> anyhow::Result<TcpStream> {
+    let mut stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((settings.host.as_str(), settings.port)))
+        .await
+        .context("timed out")??;
+    stream.write_all(&connect_packet(settings)).await?;
+
+    let (kind, body) = tokio::time::timeout(CONNECT_TIMEOUT, read_packet(&mut stream))
+        .await
+        .context("timed out waiting for CONNACK")??;
+    match (kind & 0xf0, body.get(1)) {
+        (CONNACK, Some(0)) => Ok(stream),
+        (CONNACK, Some(4)) => bail!("bad user name or password"),
+        (CONNACK, Some(5)) => bail!("not authorized"),
+        (CONNACK, code) => bail!("connection refused with code {:?}", code),
+        _ => bail!("expected CONNACK, got packet type {:#x}", kind),
+    }
+}
+
+/// Publishes messages until the channel closes, returning on errors.
+async fn publish(
+    stream: TcpStream,
+    settings: &Settings,
+    retained: &mut BTreeMap<String, Vec<u8>>,
+    messages: &mut mpsc::UnboundedReceiver<Message>,
+) -> anyhow::Result<()> {
+    let (mut reader, mut writer) = stream.into_split();
+    // Only `PINGRESP`s are expected, read to notice the broker leaving.
+    let mut incoming = tokio::spawn(async move {
+        loop {
+            if let Err(err) = read_packet(&mut reader).await {
+                return err;
+            }
+        }
+    });
+
+    for (topic, payload) in retained.iter() {
+        writer.write_all(&publish_packet(topic, payload, true)).await?;
+    }
+
+    let mut ping = tokio::time::interval(Duration::from_secs(settings.keep_alive.max(1) as u64));
+    ping.tick().await;
+    let result = loop {
+        tokio::select! {
+            message = messages.recv() => {
+                let Some(message) = message else {
+                    writer.write_all(&DISCONNECT).await?;
+                    break Ok(());
+                };
+                if message.retain {
+                    if retained.get(&message.topic) == Some(&message.payload) {
+                        continue;
+                    }
+                    retained.insert(message.topic.clone(), message.payload.clone());
+                }
+                writer.write_all(&publish_packet(&message.topic, &message.payload, message.retain)).await?;
+            }
+            _ = ping.tick() => writer.write_all(&PINGREQ).await?,
+            result = &mut incoming => break Err(result.unwrap_or_else(anyhow::Error::from)),
+        }
+    };
+    incoming.abort();
+    result
+}
+
+/// A packet of type `kind`, with the remaining length before `body`.
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![kind];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn push_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn connect_packet(settings: &Settings) -> Vec<u8> {
+    let password = settings.password.as_ref().filter(|_| settings.username.is_some());
+    // Clean session.
+    let mut flags = 0x02;
+    if settings.username.is_some() {
+        flags |= 0x80;
+    }
+    if password.is_some() {
+        flags |= 0x40;
+    }
+
+    let mut body = Vec::new();
+    push_string(&mut body, "MQTT");
+    body.push(4);
+    body.push(flags);
+    body.extend_from_slice(&settings.keep_alive.to_be_bytes());
+    push_string(&mut body, &settings.client_id);
+    for value in [settings.username.as_ref(), password].into_iter().flatten() {
+        push_string(&mut body, value);
+    }
+    packet(CONNECT, &body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, topic);
+    body.extend_from_slice(payload);
+    packet(if retain { PUBLISH | RETAIN } else { PUBLISH }, &body)
+}
+
+/// Reads the type byte and the body of a packet.
+async fn read_packet(reader: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<(u8, Vec<u8>)> {
+    let kind = reader.read_u8().await?;
+    let mut length = 0usize;
+    for shift in [0, 7, 14, 21] {
+        let byte = reader.read_u8().await?;
+        length |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            return Ok((kind, body));
+        }
+    }
+    bail!("malformed remaining length")
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Topic and payload of a `PUBLISH`, with whether it is retained.
+    fn published((kind, body): (u8, Vec<u8>)) -> (String, String, bool) {
+        assert_eq!(kind & 0xf0, PUBLISH);
+        let length = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let topic = String::from_utf8(body[2..2 + length].to_vec()).unwrap();
+        let payload = String::from_utf8(body[2 + length..].to_vec()).unwrap();
+        (topic, payload, kind & RETAIN != 0)
+    }
+
+    #[tokio::test]
+    async fn publishes_to_a_local_broker() {
+        let broker = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mqtt = Mqtt::start(Settings {
+            host: String::from("127.0.0.1"),
+            port: broker.local_addr().unwrap().port(),
+            username: Some(String::from("user")),
+            password: Some(String::from("secret")),
+            ..Default::default()
+        });
+        mqtt.send(String::from("ap/seed/Alice/goal"), b"false".to_vec(), true);
+        mqtt.send(String::from("ap/seed/Alice/goal"), b"false".to_vec(), true);
+        mqtt.send(String::from("ap/seed/Alice/item_received"), b"{}".to_vec(), false);
+
+        let (mut stream, _) = broker.accept().await.unwrap();
+        let (kind, body) = read_packet(&mut stream).await.unwrap();
+        assert_eq!(kind, CONNECT);
+        assert_eq!(body, connect_packet(&mqtt.settings)[2..]);
+        stream.write_all(&[CONNACK, 0x02, 0x00, 0x00]).await.unwrap();
+
+        let goal = published(read_packet(&mut stream).await.unwrap());
+        assert_eq!(goal, (String::from("ap/seed/Alice/goal"), String::from("false"), true));
+        // The unchanged state is not sent twice.
+        let alert = published(read_packet(&mut stream).await.unwrap());
+        assert_eq!(alert, (String::from("ap/seed/Alice/item_received"), String::from("{}"), false));
+    }
+
+    #[test]
+    fn sends_the_password_with_a_username_only() {
+        let mut settings = Settings {
+            password: Some(String::from("secret")),
+            ..Default::default()
+        };
+        let connect = connect_packet(&settings);
+        // Flags follow the protocol name and level.
+        assert_eq!(connect[2 + 6 + 1], 0x02);
+        assert!(!connect.ends_with(b"secret"));
+
+        settings.username = Some(String::from("user"));
+        let connect = connect_packet(&settings);
+        assert_eq!(connect[2 + 6 + 1], 0xc2);
+        assert!(connect.ends_with(b"\x00\x04user\x00\x06secret"));
+    }
+
+    #[test]
+    fn encodes_long_remaining_lengths() {
+        let packet = packet(PUBLISH, &[0; 321]);
+        assert_eq!(&packet[..3], &[PUBLISH, 0xc1, 0x02]);
+        assert_eq!(packet.len(), 3 + 321);
+    }
+}
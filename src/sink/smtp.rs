@@ -0,0 +1,476 @@
+//! Emails a digest of the rooms, for slow asyncs where live alerts would be
+//! too much: the items received, the hints, the players who reached their
+//! goal and the progress of the slot since the last digest.
+//!
+//! Items received and hints come from the alerts, so they follow the alert
+//! rules. Digests are sent every [`Settings::interval`] minutes and, with
+//! [`Settings::on_goal`], as soon as the slot of a session reaches its goal.
+//! Nothing is sent when nothing happened. Try it against a local SMTP catcher
+//! such as Mailpit with `"security": "plain"`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context as _};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+use crate::alert::AlertKind;
+use crate::api;
+use crate::session::Session;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// No encryption, for local servers only: the password is only sent to
+    /// a loopback host.
+    Plain,
+    /// Upgrades the connection with `STARTTLS` before logging in.
+    #[default]
+    StartTls,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub host: String,
+    pub port: u16,
+    pub security: Security,
+    /// Logs in with `AUTH PLAIN` when set.
+    pub username: Option<String>,
+    /// Kept in the secret store once it is unlocked, see
+    /// [`sink::Settings::passwords`](crate::sink::Settings::passwords).
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    /// Minutes between digests, 0 to only send them on goal.
+    pub interval: u64,
+    /// Sends the digest as soon as the slot of a session reaches its goal.
+    pub on_goal: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            host: String::from("localhost"),
+            port: 587,
+            security: Security::default(),
+            username: None,
+            password: None,
+            from: String::from("ap_alert@localhost"),
+            to: Vec::new(),
+            subject: String::from("AP_Alert digest"),
+            interval: 24 * 60,
+            on_goal: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Progress {
+    checked: usize,
+    total: usize,
+    goal: bool,
+}
+
+#[derive(Debug)]
+enum Entry {
+    Event(api::Event),
+    Progress { session: String, progress: Progress },
+}
+
+/// What happened in the room of a session since the last digest.
+#[derive(Debug, Default)]
+struct Room {
+    items: Vec<String>,
+    hints: Vec<String>,
+    goals: Vec<String>,
+    progress: Option<Progress>,
+    /// Progress in the last digest sent.
+    reported: Option<Progress>,
+}
+
+impl Room {
+    fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.hints.is_empty() && self.goals.is_empty() && self.progress == self.reported
+    }
+}
+
+#[derive(Debug, Default)]
+struct Digest {
+    rooms: BTreeMap<String, Room>,
+}
+
+impl Digest {
+    /// Adds an entry, returning whether it is the goal of the slot.
+    fn record(&mut self, entry: Entry) -> bool {
+        match entry {
+            Entry::Event(api::Event::Alert { session, kind, text }) => {
+                let room = self.rooms.entry(session).or_default();
+                match kind {
+                    AlertKind::ItemReceived => room.items.push(text),
+                    AlertKind::Hint => room.hints.push(text),
+                    _ => {}
+                }
+                false
+            }
+            Entry::Event(api::Event::Print { session, kind, text, .. }) => {
+                if kind == "Goal" {
+                    self.rooms.entry(session).or_default().goals.push(text);
+                }
+                false
+            }
+            Entry::Progress { session, progress } => {
+                let room = self.rooms.entry(session).or_default();
+                let reached = progress.goal && !room.progress.is_some_and(|progress| progress.goal);
+                room.progress = Some(progress);
+                reached
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rooms.values().all(Room::is_empty)
+    }
+
+    /// The body of the email, one part per room where something happened.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for (session, room) in self.rooms.iter().filter(|(_, room)| !room.is_empty()) {
+            let _ = writeln!(out, "{}", session);
+            if let Some(progress) = room.progress {
+                let _ = write!(out, "  {}/{} locations checked", progress.checked, progress.total);
+                if let Some(reported) = room.reported {
+                    let _ = write!(out, " (+{})", progress.checked.saturating_sub(reported.checked));
+                }
+                let _ = writeln!(out, "{}", if progress.goal { ", goal reached" } else { "" });
+            }
+            for (title, lines) in [("Items received", &room.items), ("Hints", &room.hints), ("Goals", &room.goals)] {
+                if !lines.is_empty() {
+                    let _ = writeln!(out, "  {} ({}):", title, lines.len());
+                    for line in lines {
+                        let _ = writeln!(out, "    - {}", line);
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Starts over after a digest was sent.
+    fn clear(&mut self) {
+        for room in self.rooms.values_mut() {
+            room.items.clear();
+            room.hints.clear();
+            room.goals.clear();
+            room.reported = room.progress;
+        }
+    }
+}
+
+/// Handle on the digest, which is kept and sent on its own thread.
+#[derive(Debug)]
+pub struct Smtp {
+    entries: mpsc::UnboundedSender<Entry>,
+}
+
+impl Smtp {
+    pub fn start(settings: Settings) -> Self {
+        let (entries, receiver) = mpsc::unbounded_channel();
+        let spawned = std::thread::Builder::new().name(String::from("smtp")).spawn(move || {
+            match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime.block_on(run(settings, receiver)),
+                Err(err) => error!("SMTP sink stopped: {}", err),
+            }
+        });
+        if let Err(err) = spawned {
+            error!("Could not start the SMTP sink: {}", err);
+        }
+        Self { entries }
+    }
+
+    pub fn publish_event(&self, event: &api::Event) {
+        let _ = self.entries.send(Entry::Event(event.clone()));
+    }
+
+    pub fn publish_state(&self, session: &Session) {
+        let Some(room) = &session.room else {
+            return;
+        };
+        let progress = Progress {
+            checked: room.checked_locations.len(),
+            total: room.checked_locations.len() + room.missing_locations.len(),
+            goal: session.goal,
+        };
+        let _ = self.entries.send(Entry::Progress {
+            session: session.label().to_owned(),
+            progress,
+        });
+    }
+}
+
+/// Keeps the digest and sends it when due, until the app exits. A digest
+/// that could not be sent is kept for the next time.
+async fn run(settings: Settings, mut entries: mpsc::UnboundedReceiver<Entry>) {
+    let interval = (settings.interval > 0).then(|| Duration::from_secs(settings.interval * 60));
+    let mut due = interval.map(|interval| Instant::now() + interval);
+    let mut digest = Digest::default();
+    loop {
+        let send = tokio::select! {
+            entry = entries.recv() => match entry {
+                Some(entry) => digest.record(entry) && settings.on_goal,
+                None => return,
+            },
+            _ = async {
+                match due {
+                    Some(due) => tokio::time::sleep_until(due).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                due = due.zip(interval).map(|(due, interval)| due + interval);
+                true
+            }
+        };
+        if send && !digest.is_empty() {
+            match self::send(&settings, &digest.render()).await {
+                Ok(()) => {
+                    info!("Sent the digest to {}", settings.to.join(", "));
+                    digest.clear();
+                }
+                Err(err) => error!("Could not send the digest through {}:{}: {:#}", settings.host, settings.port, err),
+            }
+        }
+    }
+}
+
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+type Stream = BufReader<Box<dyn Connection>>;
+
+/// Reads a reply, which may span several lines.
+async fn reply(stream: &mut Stream) -> anyhow::Result<(u16, String)> {
+    let mut text = String::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            bail!("connection closed");
+        }
+        let line = line.trim_end();
+        let code = line.get(..3).and_then(|code| code.parse().ok()).context("malformed reply")?;
+        text.push_str(line.get(4..).unwrap_or_default());
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok((code, text));
+        }
+        text.push(' ');
+    }
+}
+
+/// Sends a command, failing unless the reply is of the class of `expected`.
+async fn command(stream: &mut Stream, line: &str, expected: u16) -> anyhow::Result<()> {
+    stream.get_mut().write_all(format!("{}\r\n", line).as_bytes()).await?;
+    let (code, text) = reply(stream).await?;
+    if code / 100 != expected / 100 {
+        // Only the verb, not to log credentials.
+        let verb = line.split(' ').next().unwrap_or_default();
+        bail!("{} refused: {} {}", verb, code, text);
+    }
+    Ok(())
+}
+
+/// A header value, encoded when it is not plain ASCII.
+fn header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_owned()
+    } else {
+        format!("=?utf-8?B?{}?=", BASE64.encode(value))
+    }
+}
+
+/// The email, with lines starting with a dot escaped for `DATA`.
+fn message(settings: &Settings, body: &str) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        settings.from,
+        settings.to.join(", "),
+        header(&settings.subject),
+        httpdate::fmt_http_date(SystemTime::now()),
+    );
+    for line in body.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message
+}
+
+/// Whether `host` is this machine, the only one credentials are sent to in
+/// clear text.
+fn is_loopback(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+async fn send(settings: &Settings, body: &str) -> anyhow::Result<()> {
+    if settings.to.is_empty() {
+        bail!("no recipient");
+    }
+    if settings.security == Security::Plain && settings.username.is_some() && !is_loopback(&settings.host) {
+        bail!("refusing to log in to {} without encryption, use starttls", settings.host);
+    }
+    tokio::time::timeout(TIMEOUT, deliver(settings, body)).await.context("timed out")?
+}
+
+async fn deliver(settings: &Settings, body: &str) -> anyhow::Result<()> {
+    let stream = TcpStream::connect((settings.host.as_str(), settings.port)).await?;
+    let mut stream: Stream = BufReader::new(Box::new(stream));
+    let (code, text) = reply(&mut stream).await?;
+    if code != 220 {
+        bail!("server refused the connection: {} {}", code, text);
+    }
+    command(&mut stream, "EHLO localhost", 250).await?;
+
+    if settings.security == Security::StartTls {
+        command(&mut stream, "STARTTLS", 220).await?;
+        let connector = tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+        let tls = connector.connect(&settings.host, stream.into_inner()).await?;
+        stream = BufReader::new(Box::new(tls));
+        command(&mut stream, "EHLO localhost", 250).await?;
+    }
+
+    if let Some(username) = &settings.username {
+        let password = settings.password.as_deref().unwrap_or_default();
+        let credentials = BASE64.encode(format!("\0{}\0{}", username, password));
+        command(&mut stream, &format!("AUTH PLAIN {}", credentials), 235).await?;
+    }
+
+    command(&mut stream, &format!("MAIL FROM:<{}>", settings.from), 250).await?;
+    for to in &settings.to {
+        command(&mut stream, &format!("RCPT TO:<{}>", to), 250).await?;
+    }
+    command(&mut stream, "DATA", 354).await?;
+    command(&mut stream, &format!("{}.", message(settings, body)), 250).await?;
+    let _ = command(&mut stream, "QUIT", 221).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn alert(kind: AlertKind, text: &str) -> Entry {
+        Entry::Event(api::Event::Alert {
+            session: String::from("Alice"),
+            kind,
+            text: String::from(text),
+        })
+    }
+
+    fn progress(checked: usize, goal: bool) -> Entry {
+        Entry::Progress {
+            session: String::from("Alice"),
+            progress: Progress { checked, total: 10, goal },
+        }
+    }
+
+    #[test]
+    fn digests_what_happened_since_the_last_one() {
+        let mut digest = Digest::default();
+        assert!(!digest.record(progress(2, false)));
+        digest.record(alert(AlertKind::ItemReceived, "Bob sent Hookshot to Alice"));
+        digest.record(alert(AlertKind::ItemSent, "Alice sent Bow to Bob"));
+        assert_eq!(
+            digest.render(),
+            "Alice\n  2/10 locations checked\n  Items received (1):\n    - Bob sent Hookshot to Alice\n\n"
+        );
+
+        digest.clear();
+        assert!(digest.is_empty());
+        assert!(digest.record(progress(10, true)));
+        assert!(!digest.record(progress(10, true)));
+        assert_eq!(digest.render(), "Alice\n  10/10 locations checked (+8), goal reached\n\n");
+    }
+
+    #[tokio::test]
+    async fn logs_in_without_encryption_on_loopback_only() {
+        assert!(is_loopback("localhost") && is_loopback("127.0.0.1") && is_loopback("[::1]"));
+        assert!(!is_loopback("mail.example.com") && !is_loopback("192.168.1.2"));
+
+        let settings = Settings {
+            host: String::from("mail.example.com"),
+            security: Security::Plain,
+            username: Some(String::from("alice")),
+            password: Some(String::from("secret")),
+            to: vec![String::from("alice@example.com")],
+            ..Default::default()
+        };
+        let err = send(&settings, "").await.unwrap_err();
+        assert_eq!(err.to_string(), "refusing to log in to mail.example.com without encryption, use starttls");
+    }
+
+    #[tokio::test]
+    async fn sends_to_a_local_server() {
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let settings = Settings {
+            host: String::from("127.0.0.1"),
+            port: server.local_addr().unwrap().port(),
+            security: Security::Plain,
+            to: vec![String::from("alice@localhost")],
+            ..Default::default()
+        };
+        let client = tokio::spawn(async move { send(&settings, "Alice\n.hidden\n").await });
+
+        let (stream, _) = server.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        stream.get_mut().write_all(b"220 catcher\r\n").await.unwrap();
+        let mut lines = Vec::new();
+        let mut data = false;
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            let line = line.trim_end().to_owned();
+            let answer: &[u8] = match line.as_str() {
+                "EHLO localhost" => b"250-catcher\r\n250 8BITMIME\r\n",
+                "DATA" => {
+                    data = true;
+                    b"354 go ahead\r\n"
+                }
+                "." => {
+                    data = false;
+                    b"250 queued\r\n"
+                }
+                "QUIT" => b"221 bye\r\n",
+                _ if data => b"",
+                _ => b"250 ok\r\n",
+            };
+            stream.get_mut().write_all(answer).await.unwrap();
+            lines.push(line);
+            if lines.last().unwrap() == "QUIT" {
+                break;
+            }
+        }
+
+        client.await.unwrap().unwrap();
+        assert_eq!(lines[..3], ["EHLO localhost", "MAIL FROM:<ap_alert@localhost>", "RCPT TO:<alice@localhost>"]);
+        assert!(lines.contains(&String::from("Subject: AP_Alert digest")));
+        assert!(lines.ends_with(&[String::from("Alice"), String::from("..hidden"), String::from("."), String::from("QUIT")]));
+    }
+}
@@ -0,0 +1,499 @@
+//! Keeps alerts from flooding the front ends and the sinks.
+//!
+//! - A release or a collect is gathered into one alert summing up the items
+//!   it sent, such as "Alice released: 37 items, 4 to you".
+//! - An alert a session already raised for the same event is not raised
+//!   again, such as a hint the server sends again after a reconnect. Events
+//!   are told apart by location or slot, chat is never dropped.
+//! - During do not disturb or quiet hours, alerts are held and raised once
+//!   they are over.
+//! - The local API and the MQTT sink get at most [`RateLimits`] alerts a
+//!   minute, the others are dropped.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, UtcOffset};
+use tracing::debug;
+
+use crate::alert::{Alert, AlertKind};
+use crate::ap::connection::ConnectionId;
+use crate::ap::messages::PrintJSON;
+use crate::session::Session;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// How often [`Throttle::tick`] is called while it holds alerts back.
+pub const TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Seconds a release or a collect keeps gathering the items it sends
+    /// after the last one, 0 to raise every item on its own.
+    pub burst_window: u64,
+    pub do_not_disturb: bool,
+    pub quiet_hours: QuietHours,
+    pub rate_limits: RateLimits,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            burst_window: 3,
+            do_not_disturb: false,
+            quiet_hours: QuietHours::default(),
+            rate_limits: RateLimits::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Whether alerts are held for now.
+    pub fn is_quiet(&self) -> bool {
+        self.do_not_disturb || self.quiet_hours.contains(local_minute())
+    }
+}
+
+/// Alerts a minute each sink gets, 0 for no limit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+    /// Alerts pushed on the `/events` websocket.
+    pub api: u32,
+    pub mqtt: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self { api: 30, mqtt: 30 }
+    }
+}
+
+/// Local times of day as `HH:MM`, spanning midnight when `end` comes before
+/// `start`. Off while either is empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+impl QuietHours {
+    fn contains(&self, minute: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            (start..end).contains(&minute)
+        } else {
+            minute >= start || minute < end
+        }
+    }
+}
+
+/// Minutes since midnight of a `HH:MM` time.
+pub fn parse_time(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+static LOCAL_OFFSET: OnceLock<UtcOffset> = OnceLock::new();
+
+/// Reads the offset of local time, which can only be done safely while the
+/// app runs a single thread. Quiet hours are in UTC when it could not be.
+pub fn init_local_offset() {
+    if let Ok(offset) = UtcOffset::current_local_offset() {
+        let _ = LOCAL_OFFSET.set(offset);
+    }
+}
+
+pub fn local_offset() -> UtcOffset {
+    LOCAL_OFFSET.get().copied().unwrap_or(UtcOffset::UTC)
+}
+
+pub fn local_minute() -> u32 {
+    let now = OffsetDateTime::now_utc().to_offset(local_offset());
+    now.hour() as u32 * 60 + now.minute() as u32
+}
+
+/// Alerts sent to a sink over the last minute.
+#[derive(Debug, Default)]
+pub struct RateLimit {
+    per_minute: u32,
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimit {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            sent: VecDeque::new(),
+        }
+    }
+
+    /// Whether an alert can be sent at `now`, counting it if so.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        if self.per_minute == 0 {
+            return true;
+        }
+        while self.sent.front().is_some_and(|sent| now.duration_since(*sent) >= MINUTE) {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= self.per_minute as usize {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BurstKind {
+    Release,
+    Collect,
+}
+
+/// Items sent by a release or a collect of the room of a session.
+#[derive(Debug)]
+struct Burst {
+    id: ConnectionId,
+    source: String,
+    kind: BurstKind,
+    slot: u32,
+    player: String,
+    items: usize,
+    /// Items that raised an alert, those sent to or from the session's slot.
+    mine: usize,
+    /// Kind of the summary, none when no alert was raised.
+    alert: Option<AlertKind>,
+    until: Instant,
+}
+
+impl Burst {
+    fn matches(&self, id: ConnectionId, print: &PrintJSON) -> bool {
+        let PrintJSON::ItemSend { receiving, item, .. } = print else {
+            return false;
+        };
+        self.id == id
+            && match self.kind {
+                BurstKind::Release => item.player == self.slot,
+                BurstKind::Collect => *receiving == self.slot,
+            }
+    }
+
+    fn summary(&self, now: Instant) -> Option<Alert> {
+        let items = if self.items == 1 {
+            String::from("1 item")
+        } else {
+            format!("{} items", self.items)
+        };
+        let text = match self.kind {
+            BurstKind::Release => format!("{} released: {}, {} to you", self.player, items, self.mine),
+            BurstKind::Collect => format!("{} collected: {}, {} from you", self.player, items, self.mine),
+        };
+        Some(Alert {
+            kind: self.alert?,
+            source: self.source.clone(),
+            text,
+            at: now,
+            log_index: None,
+        })
+    }
+}
+
+/// What an alert was raised for, as far as the server may send it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum EventKey {
+    /// An `ItemSend` or `Hint`, by finding player and location.
+    Location { hint: bool, player: u32, location: i64 },
+    /// A `Goal`, `Release` or `Collect` of a slot.
+    Slot { kind: &'static str, team: u32, slot: u32 },
+}
+
+impl EventKey {
+    /// Key of the messages sent again after a reconnect, none for the
+    /// others such as chat.
+    fn of(print: &PrintJSON) -> Option<Self> {
+        Some(match print {
+            PrintJSON::ItemSend { item, .. } | PrintJSON::Hint { item, .. } => EventKey::Location {
+                hint: matches!(print, PrintJSON::Hint { .. }),
+                player: item.player,
+                location: item.location,
+            },
+            PrintJSON::Goal { team, slot, .. } | PrintJSON::Release { team, slot, .. } | PrintJSON::Collect { team, slot, .. } => {
+                EventKey::Slot {
+                    kind: print.kind(),
+                    team: *team,
+                    slot: *slot,
+                }
+            }
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Throttle {
+    /// Kind and event of the alerts raised by each session, bounded by the
+    /// locations and slots of its room.
+    seen: BTreeMap<ConnectionId, HashSet<(AlertKind, EventKey)>>,
+    bursts: Vec<Burst>,
+    /// Alerts held during do not disturb or quiet hours, oldest first.
+    pub held: Vec<(ConnectionId, Alert)>,
+}
+
+impl Throttle {
+    /// Takes a message of session `id` with the alert it raised, returning
+    /// the alerts to raise now.
+    pub fn push(
+        &mut self,
+        settings: &Settings,
+        id: ConnectionId,
+        session: &Session,
+        print: Option<&PrintJSON>,
+        alert: Option<Alert>,
+        now: Instant,
+    ) -> Vec<(ConnectionId, Alert)> {
+        let seen = self.seen.entry(id).or_default();
+        let key = print.and_then(EventKey::of);
+        let mut alert = alert.filter(|alert| key.map_or(true, |key| seen.insert((alert.kind, key))));
+
+        let window = Duration::from_secs(settings.burst_window);
+        let room = session.room.as_ref().filter(|_| !window.is_zero());
+        if let (Some(print), Some(room)) = (print, room) {
+            match print {
+                PrintJSON::Release { team, slot, .. } | PrintJSON::Collect { team, slot, .. } if *team == room.team => {
+                    let kind = match print {
+                        PrintJSON::Release { .. } => BurstKind::Release,
+                        _ => BurstKind::Collect,
+                    };
+                    let burst = self.burst(id, session, kind, *slot);
+                    if let Some(alert) = alert.take() {
+                        burst.alert = Some(alert.kind);
+                    }
+                    burst.until = now + window;
+                }
+                _ => {
+                    if let Some(burst) = self.bursts.iter_mut().find(|burst| burst.matches(id, print)) {
+                        burst.items += 1;
+                        if alert.is_some() {
+                            burst.mine += 1;
+                        }
+                        // Urgent alerts are raised right away, yet counted.
+                        if let Some(kind) = alert.as_ref().map(|alert| alert.kind).filter(|kind| !kind.is_urgent()) {
+                            burst.alert.get_or_insert(kind);
+                            alert = None;
+                        }
+                        burst.until = now + window;
+                    }
+                }
+            }
+        }
+
+        self.raise(settings, alert.map(|alert| (id, alert)).into_iter().collect())
+    }
+
+    /// The running burst of `slot`, started if there is none.
+    fn burst(&mut self, id: ConnectionId, session: &Session, kind: BurstKind, slot: u32) -> &mut Burst {
+        let running = self
+            .bursts
+            .iter()
+            .position(|burst| burst.id == id && burst.kind == kind && burst.slot == slot);
+        let index = running.unwrap_or_else(|| {
+            self.bursts.push(Burst {
+                id,
+                source: session.label().to_owned(),
+                kind,
+                slot,
+                player: session
+                    .player_name(slot)
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| format!("Player {}", slot)),
+                items: 0,
+                mine: 0,
+                alert: None,
+                until: Instant::now(),
+            });
+            self.bursts.len() - 1
+        });
+        &mut self.bursts[index]
+    }
+
+    /// Raises the bursts that are over and, once quiet hours are, the held
+    /// alerts.
+    pub fn tick(&mut self, settings: &Settings, now: Instant) -> Vec<(ConnectionId, Alert)> {
+        let (over, running): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.bursts).into_iter().partition(|burst| burst.until <= now);
+        self.bursts = running;
+        let alerts = over
+            .iter()
+            .filter_map(|burst| burst.summary(now).map(|alert| (burst.id, alert)))
+            .collect();
+        self.raise(settings, alerts)
+    }
+
+    fn raise(&mut self, settings: &Settings, alerts: Vec<(ConnectionId, Alert)>) -> Vec<(ConnectionId, Alert)> {
+        if settings.is_quiet() {
+            let (urgent, held): (Vec<_>, Vec<_>) = alerts.into_iter().partition(|(_, alert)| alert.kind.is_urgent());
+            if !held.is_empty() {
+                debug!("Holding {} alerts", held.len());
+            }
+            self.held.extend(held);
+            return urgent;
+        }
+        let mut raised = std::mem::take(&mut self.held);
+        raised.extend(alerts);
+        raised
+    }
+
+    /// Whether [`Throttle::tick`] has anything to raise later.
+    pub fn is_pending(&self) -> bool {
+        !self.bursts.is_empty() || !self.held.is_empty()
+    }
+
+    /// Drops what is kept about a stopped session.
+    pub fn forget(&mut self, id: ConnectionId) {
+        self.seen.remove(&id);
+        self.bursts.retain(|burst| burst.id != id);
+        self.held.retain(|(held, _)| *held != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::ap::connection::{Event, Source};
+    use crate::profile::Profile;
+
+    fn print(value: serde_json::Value) -> PrintJSON {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn item_send(receiving: u32, player: u32, location: u64) -> PrintJSON {
+        print(json!({
+            "type": "ItemSend",
+            "data": [{"text": format!("location {}", location)}],
+            "receiving": receiving,
+            "item": {"item": 1, "location": location, "player": player, "flags": 0},
+        }))
+    }
+
+    fn alert(kind: AlertKind, text: &str) -> Alert {
+        Alert {
+            kind,
+            source: String::from("Alice"),
+            text: String::from(text),
+            at: Instant::now(),
+            log_index: None,
+        }
+    }
+
+    fn session() -> Session {
+        let mut session = Session::new(Profile::default(), Source::Live { record: None });
+        let connected = json!({
+            "cmd": "Connected",
+            "team": 0,
+            "slot": 1,
+            "players": [{ "team": 0, "slot": 2, "alias": "Bob", "name": "Bob" }],
+            "missing_locations": [],
+            "checked_locations": [],
+            "hint_points": 0,
+            "slot_info": {},
+        });
+        session.process(&Event::APMessage(serde_json::from_value(connected).unwrap()));
+        session
+    }
+
+    #[test]
+    fn sums_up_releases() {
+        let (mut throttle, settings, session, id) = (Throttle::default(), Settings::default(), session(), ConnectionId(0));
+        let now = Instant::now();
+        let release = print(json!({"type": "Release", "data": [], "team": 0, "slot": 2}));
+        let raised = throttle.push(&settings, id, &session, Some(&release), Some(alert(AlertKind::Release, "released")), now);
+        assert!(raised.is_empty());
+        for location in 0..5 {
+            let received = (location < 2).then(|| alert(AlertKind::ItemReceived, &location.to_string()));
+            assert!(throttle.push(&settings, id, &session, Some(&item_send(location as u32 % 3 + 1, 2, location)), received, now).is_empty());
+        }
+        // Items found by someone else are not part of the release.
+        let other = throttle.push(&settings, id, &session, Some(&item_send(1, 3, 9)), Some(alert(AlertKind::ItemReceived, "9")), now);
+        assert_eq!(other.len(), 1);
+
+        assert!(throttle.tick(&settings, now).is_empty());
+        let summary = throttle.tick(&settings, now + Duration::from_secs(3));
+        assert_eq!(summary[0].1.text, "Bob released: 5 items, 2 to you");
+        assert_eq!(summary[0].1.kind, AlertKind::Release);
+        assert!(!throttle.is_pending());
+    }
+
+    #[test]
+    fn drops_repeats_and_holds_alerts_while_quiet() {
+        let (mut throttle, mut settings, session, id) = (Throttle::default(), Settings::default(), session(), ConnectionId(0));
+        let now = Instant::now();
+        settings.do_not_disturb = true;
+        let hint = print(json!({
+            "type": "Hint",
+            "data": [{"text": "hint"}],
+            "receiving": 1,
+            "item": {"item": 1, "location": 7, "player": 2, "flags": 0},
+            "found": false,
+        }));
+        throttle.push(&settings, id, &session, Some(&hint), Some(alert(AlertKind::Hint, "hint")), now);
+        throttle.push(&settings, id, &session, Some(&hint), Some(alert(AlertKind::Hint, "hint")), now);
+        assert_eq!(throttle.held.len(), 1);
+        assert!(throttle.tick(&settings, now).is_empty());
+
+        let urgent = throttle.push(&settings, id, &session, None, Some(alert(AlertKind::Watched, "Hookshot")), now);
+        assert_eq!(urgent.len(), 1);
+
+        settings.do_not_disturb = false;
+        assert_eq!(throttle.tick(&settings, now).len(), 1);
+        assert!(!throttle.is_pending());
+    }
+
+    #[test]
+    fn raises_repeated_chat_mentions() {
+        let (mut throttle, settings, session, id) = (Throttle::default(), Settings::default(), session(), ConnectionId(0));
+        let now = Instant::now();
+        let chat = print(json!({
+            "type": "Chat",
+            "data": [{"text": "Bob: @Alice ready?"}],
+            "team": 0,
+            "slot": 2,
+            "message": "@Alice ready?",
+        }));
+        for _ in 0..2 {
+            let mention = Some(alert(AlertKind::Mention, "Bob: @Alice ready?"));
+            assert_eq!(throttle.push(&settings, id, &session, Some(&chat), mention, now).len(), 1);
+        }
+        assert!(throttle.seen[&id].is_empty());
+    }
+
+    #[test]
+    fn limits_alerts_a_minute() {
+        let mut limit = RateLimit::new(2);
+        let now = Instant::now();
+        assert!(limit.allow(now) && limit.allow(now));
+        assert!(!limit.allow(now + Duration::from_secs(59)));
+        assert!(limit.allow(now + MINUTE));
+    }
+
+    #[test]
+    fn quiet_hours_span_midnight() {
+        let hours = |start: &str, end: &str| QuietHours {
+            start: String::from(start),
+            end: String::from(end),
+        };
+        assert!(hours("22:00", "08:00").contains(23 * 60));
+        assert!(hours("22:00", "08:00").contains(7 * 60 + 59));
+        assert!(!hours("22:00", "08:00").contains(8 * 60));
+        assert!(hours("13:00", "14:30").contains(14 * 60));
+        assert!(!hours("", "08:00").contains(0));
+    }
+}
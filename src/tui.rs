@@ -0,0 +1,253 @@
+mod alerts;
+mod dashboard;
+mod data_storage;
+mod hints;
+mod login;
+mod settings;
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fs::File;
+
+use anyhow::Context as _;
+use crossterm::event::{Event as TermEvent, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures_util::StreamExt;
+use iced::futures::channel::mpsc;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+use crate::ap::connection::{self, ConnectionId, Event};
+use crate::cli::Cli;
+use crate::config;
+use crate::context::Context;
+use crate::headless;
+use crate::throttle;
+use crate::update::{Message, Route, Screen, State};
+
+const LOG_FILE_NAME: &str = "tui.log";
+
+/// What a screen asks for after handling a key.
+pub enum Action {
+    None,
+    Dispatch(Message),
+    Quit,
+}
+
+struct Tui {
+    state: State,
+    /// Terminal only bits of the screens, reset with them.
+    form: login::Form,
+    view: dashboard::View,
+    data_storage: data_storage::View,
+    /// Lines scrolled up on the hints and alerts screens.
+    scroll: usize,
+    sender: mpsc::Sender<(ConnectionId, Event)>,
+    workers: BTreeMap<ConnectionId, JoinHandle<Infallible>>,
+}
+
+/// Logs go to a file in the config folder, they would garble the screen.
+pub fn log_file() -> anyhow::Result<File> {
+    let path = config::config_dir()?.join(LOG_FILE_NAME);
+    File::create(&path).with_context(|| format!("could not create {}", path.display()))
+}
+
+/// Runs the terminal front end until the user quits.
+pub fn run(cli: Cli) -> anyhow::Result<()> {
+    tokio::runtime::Runtime::new()?.block_on(run_async(cli))
+}
+
+async fn run_async(cli: Cli) -> anyhow::Result<()> {
+    let mut context = Context::try_load_from_save();
+    if let Some(passphrase) = &cli.passphrase {
+        if let Err(err) = context.unlock_secrets(passphrase) {
+            context.report(err);
+        }
+    }
+
+    if let Err(err) = context.apply_cli(&cli) {
+        context.report(err);
+    }
+
+    if !cli.profiles.is_empty() || cli.server.is_some() {
+        for profile in headless::selected_profiles(&context, &cli)? {
+            context.start_session_for(profile);
+        }
+    }
+
+    let (sender, mut receiver) = mpsc::channel(100);
+    let mut tui = Tui {
+        state: State::new(context),
+        form: login::Form::default(),
+        view: dashboard::View::default(),
+        data_storage: data_storage::View::default(),
+        scroll: 0,
+        sender,
+        workers: BTreeMap::new(),
+    };
+    tui.sync_workers();
+
+    let mut terminal = ratatui::init();
+    let result = tui.event_loop(&mut terminal, &mut receiver).await;
+    ratatui::restore();
+
+    info!("Shutting down");
+    headless::disconnect_all(&mut tui.state.context, &mut receiver).await;
+    result
+}
+
+impl Tui {
+    async fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        receiver: &mut mpsc::Receiver<(ConnectionId, Event)>,
+    ) -> anyhow::Result<()> {
+        let mut terminal_events = EventStream::new();
+        let mut tick = tokio::time::interval(throttle::TICK);
+
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            tokio::select! {
+                Some((id, event)) = receiver.next() => self.dispatch(Message::WSEvent(id, event)),
+                now = tick.tick() => self.dispatch(Message::Tick(now.into_std())),
+                terminal_event = terminal_events.next() => match terminal_event {
+                    Some(Ok(TermEvent::Key(key))) if key.kind == KeyEventKind::Press => {
+                        if let Action::Quit = self.handle_key(key) {
+                            self.dispatch(Message::Quit);
+                            return Ok(());
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err.into()),
+                    None => {
+                        self.dispatch(Message::Quit);
+                        return Ok(());
+                    }
+                },
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let context = &self.state.context;
+        let action = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
+            KeyCode::Delete if !context.errors.is_empty() => Action::Dispatch(Message::DismissError(0)),
+            KeyCode::F(n) if (1..=Route::ALL.len() as u8).contains(&n) => {
+                Action::Dispatch(Message::Navigate(Route::ALL[n as usize - 1]))
+            }
+            KeyCode::Esc if !self.state.history.is_empty() => Action::Dispatch(Message::Back),
+            _ => match &self.state.screen {
+                Screen::Login(login) => self.form.handle_key(key, login, context),
+                Screen::Dashboard(dashboard) => self.view.handle_key(key, dashboard, context),
+                Screen::DataStorage(data_storage) => self.data_storage.handle_key(key, data_storage, context),
+                Screen::Settings => settings::handle_key(key, context),
+                Screen::Hints | Screen::Alerts(_) => {
+                    match key.code {
+                        KeyCode::Up => self.scroll += 1,
+                        KeyCode::Down => self.scroll = self.scroll.saturating_sub(1),
+                        KeyCode::PageUp => self.scroll += 10,
+                        KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(10),
+                        _ => {}
+                    }
+                    Action::None
+                }
+            },
+        };
+
+        match action {
+            Action::Dispatch(message) => {
+                self.dispatch(message);
+                Action::None
+            }
+            action => action,
+        }
+    }
+
+    fn dispatch(&mut self, message: Message) {
+        let route = self.state.screen.route();
+        self.state.dispatch(message);
+        if self.state.screen.route() != route {
+            self.form = login::Form::default();
+            self.view = dashboard::View::default();
+            self.data_storage = data_storage::View::default();
+            self.scroll = 0;
+        }
+        self.sync_workers();
+    }
+
+    /// Starts the workers of new sessions and stops those of closed ones,
+    /// like the GUI subscriptions do.
+    fn sync_workers(&mut self) {
+        let sessions = &self.state.context.sessions;
+        self.workers.retain(|id, worker| {
+            let running = sessions.contains_key(id);
+            if !running {
+                worker.abort();
+            }
+            running
+        });
+        for (id, session) in sessions {
+            if !self.workers.contains_key(id) {
+                let worker = tokio::spawn(connection::run(*id, session.source.clone(), self.sender.clone()));
+                self.workers.insert(*id, worker);
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let context = &self.state.context;
+        let errors: Vec<Line> = context
+            .errors
+            .iter()
+            .map(|err| Line::styled(err.as_str(), Style::default().fg(Color::Red)))
+            .collect();
+        let [errors_area, navigation_area, screen_area] = Layout::vertical([
+            Constraint::Length(errors.len() as u16),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(Paragraph::new(errors), errors_area);
+        let routes = Route::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, route)| format!("F{} {}", index + 1, route));
+        frame.render_widget(
+            Tabs::new(routes)
+                .select(Route::ALL.iter().position(|route| *route == self.state.screen.route()))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            navigation_area,
+        );
+        match &self.state.screen {
+            Screen::Login(login) => self.form.draw(frame, screen_area, login, context),
+            Screen::Dashboard(dashboard) => self.view.draw(frame, screen_area, dashboard, context),
+            Screen::Hints => hints::draw(frame, screen_area, context, self.scroll),
+            Screen::Alerts(_) => alerts::draw(frame, screen_area, context, self.scroll),
+            Screen::DataStorage(data_storage) => {
+                self.data_storage.draw(frame, screen_area, data_storage, context)
+            }
+            Screen::Settings => settings::draw(frame, screen_area, context),
+        }
+    }
+}
+
+/// Applies a key to a single line text input, returning the edited text if
+/// the key was used.
+pub fn edit(input: &str, key: KeyEvent) -> Option<String> {
+    let mut input = input.to_owned();
+    match key.code {
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => input.push(c),
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        _ => return None,
+    }
+    Some(input)
+}
@@ -0,0 +1,21 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use crate::context::Context;
+
+use super::dashboard::{alert_lines, tail};
+
+/// Alerts of every session, newest at the bottom.
+pub fn draw(frame: &mut Frame, area: Rect, context: &Context, scroll: usize) {
+    let [alerts_area, help_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+
+    let block = match context.throttle.held.len() {
+        0 => Block::bordered().title(" Alerts "),
+        held => Block::bordered().title(format!(" Alerts ({} held) ", held)),
+    };
+    let height = block.inner(alerts_area).height;
+    let alerts = alert_lines(context.alerts.iter().map(|(_, alert)| alert));
+    frame.render_widget(tail(alerts, height, scroll).block(block), alerts_area);
+    frame.render_widget(Paragraph::new("Up/Down: scroll  Esc: back  Ctrl-C: quit"), help_area);
+}
@@ -0,0 +1,328 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, Tabs};
+use ratatui::Frame;
+
+use crate::alert::Alert;
+use crate::ap::connection::ConnectionId;
+use crate::ap::messages::{Connected, PrintJSON, SlotType};
+use crate::context::Context;
+use crate::session::{Section, Session};
+use crate::update::{Dashboard, Message, Route};
+
+use super::{edit, Action};
+
+/// Scrolling of the dashboard screen.
+#[derive(Default)]
+pub struct View {
+    /// Lines scrolled up from the newest ones.
+    scroll: usize,
+}
+
+fn player_table(room: &Connected) -> Table<'_> {
+    let rows = room.slot_info.iter().map(|(slot, info)| {
+        let alias = room
+            .players
+            .iter()
+            .find(|player| player.team == room.team && player.slot == *slot)
+            .map(|player| player.alias.clone())
+            .unwrap_or_default();
+        let members = match info.r#type {
+            SlotType::Group => info
+                .group_members
+                .iter()
+                .map(|member| {
+                    room.slot_info
+                        .get(member)
+                        .map(|member| member.name.clone())
+                        .unwrap_or_else(|| member.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => String::new(),
+        };
+
+        Row::new([
+            slot.to_string(),
+            info.name.clone(),
+            alias,
+            info.game.clone(),
+            info.r#type.to_string(),
+            members,
+        ])
+    });
+
+    Table::new(
+        rows,
+        [
+            Constraint::Length(5),
+            Constraint::Length(16),
+            Constraint::Length(16),
+            Constraint::Length(24),
+            Constraint::Length(10),
+            Constraint::Min(0),
+        ],
+    )
+    .header(
+        Row::new(["Slot", "Name", "Alias", "Game", "Type", "Members"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+}
+
+/// The newest lines that fit in `height`, skipping the `scroll` newest.
+pub fn tail<T: Clone + Into<Line<'static>>>(lines: Vec<T>, height: u16, scroll: usize) -> Paragraph<'static> {
+    let end = lines.len().saturating_sub(scroll);
+    let start = end.saturating_sub(height as usize);
+    Paragraph::new(lines[start..end].iter().cloned().map(Into::into).collect::<Vec<_>>())
+}
+
+/// A message of the log, in color when flagged by
+/// [`Session::highlighted_log`].
+fn log_line(session: &Session, (print, flagged): (&PrintJSON, bool)) -> Line<'static> {
+    let line = Line::from(session.render(print.data()));
+    if flagged {
+        line.style(Style::default().fg(Color::Yellow))
+    } else {
+        line
+    }
+}
+
+pub fn alert_lines<'a>(alerts: impl Iterator<Item = &'a Alert>) -> Vec<String> {
+    alerts
+        .map(|alert| format!("[{}] [{}] {}", alert.source, alert.kind, alert.text))
+        .collect()
+}
+
+fn session_status(session: &Session) -> String {
+    match &session.room {
+        Some(room) => format!(
+            "Connected - {}/{} checks",
+            room.checked_locations.len(),
+            room.checked_locations.len() + room.missing_locations.len()
+        ),
+        None => String::from("Connecting"),
+    }
+}
+
+impl View {
+    pub fn handle_key(&mut self, key: KeyEvent, dashboard: &Dashboard, context: &Context) -> Action {
+        let tabs: Vec<Option<ConnectionId>> = std::iter::once(None)
+            .chain(context.sessions.keys().copied().map(Some))
+            .collect();
+        let current = tabs.iter().position(|tab| *tab == dashboard.selected).unwrap_or_default();
+        let session = dashboard.selected.and_then(|id| context.sessions.get(&id));
+        let chatting = session.is_some() && dashboard.section == Section::Chat;
+        let section = Section::ALL.iter().position(|section| *section == dashboard.section).unwrap_or_default();
+
+        let message = match key.code {
+            KeyCode::Tab => Some(Message::DashboardTabSelected(tabs[(current + 1) % tabs.len()])),
+            KeyCode::BackTab => Some(Message::DashboardTabSelected(tabs[(current + tabs.len() - 1) % tabs.len()])),
+            KeyCode::Right if session.is_some() => Some(Message::DashboardSectionSelected(
+                Section::ALL[(section + 1) % Section::ALL.len()],
+            )),
+            KeyCode::Left if session.is_some() => Some(Message::DashboardSectionSelected(
+                Section::ALL[(section + Section::ALL.len() - 1) % Section::ALL.len()],
+            )),
+            KeyCode::Up => {
+                self.scroll += 1;
+                None
+            }
+            KeyCode::Down => {
+                self.scroll = self.scroll.saturating_sub(1);
+                None
+            }
+            KeyCode::PageUp => {
+                self.scroll += 10;
+                None
+            }
+            KeyCode::PageDown => {
+                self.scroll = self.scroll.saturating_sub(10);
+                None
+            }
+            KeyCode::Esc => Some(Message::Navigate(Route::Login)),
+            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Message::Disconnect),
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => dashboard
+                .selected
+                .filter(|_| session.is_some_and(|session| session.is_replay()))
+                .map(Message::StepReplay),
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                dashboard.selected.map(Message::CloseSession)
+            }
+            KeyCode::Enter if chatting => Some(Message::SendChat),
+            _ if chatting => edit(&dashboard.chat_input, key).map(Message::ChatInputChanged),
+            KeyCode::Char('q') => return Action::Quit,
+            _ => None,
+        };
+
+        if matches!(message, Some(Message::DashboardTabSelected(_) | Message::DashboardSectionSelected(_))) {
+            self.scroll = 0;
+        }
+        message.map_or(Action::None, Action::Dispatch)
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect, dashboard: &Dashboard, context: &Context) {
+        let [tabs_area, content_area, help_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let titles: Vec<String> = std::iter::once(String::from("All"))
+            .chain(context.sessions.values().map(|session| session.label().to_owned()))
+            .collect();
+        let selected = std::iter::once(None)
+            .chain(context.sessions.keys().copied().map(Some))
+            .position(|tab| tab == dashboard.selected)
+            .unwrap_or_default();
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(selected)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            tabs_area,
+        );
+
+        match dashboard
+            .selected
+            .and_then(|id| context.sessions.get(&id).map(|session| (id, session)))
+        {
+            Some((id, session)) => self.draw_session(frame, content_area, dashboard, id, session, context),
+            None => self.draw_combined(frame, content_area, context),
+        }
+
+        let replay = dashboard
+            .selected
+            .and_then(|id| context.sessions.get(&id))
+            .is_some_and(|session| session.is_replay());
+        let help = if replay {
+            "Tab: room  Left/Right: section  Up/Down: scroll  Ctrl-N: next frame  Ctrl-W: close  Ctrl-D: disconnect  Esc: back  Ctrl-C: quit"
+        } else if dashboard.selected.is_some() {
+            "Tab: room  Left/Right: section  Up/Down: scroll  Ctrl-W: close  Ctrl-D: disconnect  Esc: back  Del: dismiss error  Ctrl-C: quit"
+        } else {
+            "Tab: room  Up/Down: scroll  Ctrl-D: disconnect  Esc: back  Del: dismiss error  q: quit"
+        };
+        frame.render_widget(Paragraph::new(help), help_area);
+    }
+
+    fn draw_combined(&self, frame: &mut Frame, area: Rect, context: &Context) {
+        let [rooms_area, alerts_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+
+        let rooms = context.sessions.values().map(|session| {
+            Row::new([
+                session.label().to_owned(),
+                session.profile.connection_info.slot.clone(),
+                session.profile.connection_info.server.clone(),
+                session_status(session),
+            ])
+        });
+        frame.render_widget(
+            Table::new(
+                rooms,
+                [
+                    Constraint::Length(20),
+                    Constraint::Length(16),
+                    Constraint::Length(26),
+                    Constraint::Min(0),
+                ],
+            )
+            .header(
+                Row::new(["Room", "Slot", "Server", "Status"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::bordered().title(" Rooms ")),
+            rooms_area,
+        );
+
+        let block = Block::bordered().title(" Alerts ");
+        let height = block.inner(alerts_area).height;
+        let alerts = alert_lines(context.alerts.iter().map(|(_, alert)| alert));
+        frame.render_widget(tail(alerts, height, self.scroll).block(block), alerts_area);
+    }
+
+    fn draw_session(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        dashboard: &Dashboard,
+        id: ConnectionId,
+        session: &Session,
+        context: &Context,
+    ) {
+        let [header_area, sections_area, section_area, alerts_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Fill(2),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Slot: {} - Server: {} - {}",
+                session.profile.connection_info.slot,
+                session.profile.connection_info.server,
+                session_status(session)
+            )),
+            header_area,
+        );
+        frame.render_widget(
+            Tabs::new(Section::ALL.map(|section| section.to_string()))
+                .select(Section::ALL.iter().position(|section| *section == dashboard.section))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            sections_area,
+        );
+
+        let block = Block::bordered().title(format!(" {} ", dashboard.section));
+        let inner = block.inner(section_area);
+        match (&session.room, dashboard.section) {
+            (_, Section::Log) => {
+                let log = session.highlighted_log().map(|line| log_line(session, line)).collect();
+                frame.render_widget(tail(log, inner.height, self.scroll).block(block), section_area);
+            }
+            (_, Section::Chat) => {
+                let [chat_area, input_area] =
+                    Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(inner);
+                let chat = session.chat().map(|line| log_line(session, line)).collect();
+                frame.render_widget(block, section_area);
+                frame.render_widget(tail(chat, chat_area.height, self.scroll), chat_area);
+                frame.render_widget(Paragraph::new(format!("> {}", dashboard.chat_input)), input_area);
+                frame.set_cursor_position(Position::new(
+                    input_area.x + 2 + dashboard.chat_input.chars().count() as u16,
+                    input_area.y,
+                ));
+            }
+            (None, _) => frame.render_widget(Paragraph::new("Not connected").block(block), section_area),
+            (Some(room), Section::Players) => frame.render_widget(player_table(room).block(block), section_area),
+            (Some(_), Section::Hints) => {
+                let hints = session.hints.iter().map(|hint| session.hint_text(hint)).collect();
+                frame.render_widget(tail(hints, inner.height, self.scroll).block(block), section_area);
+            }
+            (Some(room), Section::SlotData) => {
+                let slot_data = match &room.slot_data {
+                    Some(slot_data) => serde_json::to_string_pretty(slot_data).unwrap_or_default(),
+                    None => String::from("No slot data"),
+                };
+                frame.render_widget(
+                    Paragraph::new(slot_data)
+                        .scroll((self.scroll as u16, 0))
+                        .block(block),
+                    section_area,
+                );
+            }
+        }
+
+        let block = Block::bordered().title(" Alerts ");
+        let height = block.inner(alerts_area).height;
+        let alerts = alert_lines(
+            context
+                .alerts
+                .iter()
+                .filter(|(source, _)| *source == id)
+                .map(|(_, alert)| alert),
+        );
+        frame.render_widget(tail(alerts, height, 0).block(block), alerts_area);
+    }
+}
@@ -0,0 +1,107 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::Frame;
+
+use crate::context::Context;
+use crate::update::{DataStorage, Message};
+
+use super::{edit, Action};
+
+/// Highlighted key of the data storage screen.
+#[derive(Default)]
+pub struct View {
+    focus: usize,
+}
+
+impl View {
+    pub fn handle_key(&mut self, key: KeyEvent, data_storage: &DataStorage, context: &Context) -> Action {
+        let selected = data_storage.session(context);
+        let Some(session) = selected.and_then(|id| context.sessions.get(&id)) else {
+            return Action::None;
+        };
+        let ids: Vec<_> = context.sessions.keys().copied().collect();
+        let current = ids.iter().position(|id| Some(*id) == selected).unwrap_or_default();
+
+        let message = match key.code {
+            KeyCode::Tab => {
+                self.focus = 0;
+                Some(Message::DataStorageTabSelected(ids[(current + 1) % ids.len()]))
+            }
+            KeyCode::BackTab => {
+                self.focus = 0;
+                Some(Message::DataStorageTabSelected(ids[(current + ids.len() - 1) % ids.len()]))
+            }
+            KeyCode::Up => {
+                self.focus = self.focus.saturating_sub(1);
+                None
+            }
+            KeyCode::Down => {
+                self.focus = (self.focus + 1).min(session.data_storage.len().saturating_sub(1));
+                None
+            }
+            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => session
+                .data_storage
+                .keys()
+                .nth(self.focus)
+                .map(|key| Message::UnwatchKey(key.clone())),
+            KeyCode::Enter => Some(Message::WatchKey),
+            _ => edit(&data_storage.key_input, key).map(Message::DataStorageKeyInputChanged),
+        };
+        message.map_or(Action::None, Action::Dispatch)
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect, data_storage: &DataStorage, context: &Context) {
+        let [tabs_area, input_area, keys_area, help_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let selected = data_storage.session(context);
+        let Some(session) = selected.and_then(|id| context.sessions.get(&id)) else {
+            frame.render_widget(
+                Paragraph::new("Not connected").block(Block::bordered().title(" Data storage ")),
+                keys_area,
+            );
+            return;
+        };
+
+        frame.render_widget(
+            Tabs::new(context.sessions.values().map(|session| session.label().to_owned()))
+                .select(context.sessions.keys().position(|id| Some(*id) == selected))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            tabs_area,
+        );
+
+        let block = Block::bordered().title(" Watch key ");
+        let inner = block.inner(input_area);
+        frame.render_widget(Paragraph::new(data_storage.key_input.as_str()).block(block), input_area);
+        frame.set_cursor_position(Position::new(
+            inner.x + data_storage.key_input.chars().count() as u16,
+            inner.y,
+        ));
+
+        let rows = session.data_storage.iter().map(|(key, value)| {
+            let value = match value {
+                Some(value) => value.to_string(),
+                None => String::from("Waiting for the server"),
+            };
+            Row::new([key.clone(), value])
+        });
+        let table = Table::new(rows, [Constraint::Length(30), Constraint::Min(0)])
+            .header(Row::new(["Key", "Value"]).style(Style::default().add_modifier(Modifier::BOLD)))
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered().title(" Data storage "));
+        let mut state = TableState::default().with_selected(Some(self.focus));
+        frame.render_stateful_widget(table, keys_area, &mut state);
+
+        frame.render_widget(
+            Paragraph::new("Tab: room  Enter: watch  Up/Down: key  Ctrl-X: remove  Esc: back  Ctrl-C: quit"),
+            help_area,
+        );
+    }
+}
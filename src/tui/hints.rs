@@ -0,0 +1,29 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use crate::context::Context;
+
+use super::dashboard::tail;
+
+/// Hints of every session, the ones not found yet last so that they are
+/// shown first.
+pub fn draw(frame: &mut Frame, area: Rect, context: &Context, scroll: usize) {
+    let [hints_area, help_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+
+    let mut hints: Vec<_> = context
+        .sessions
+        .values()
+        .flat_map(|session| session.hints.iter().map(move |hint| (session, hint)))
+        .collect();
+    hints.sort_by_key(|(_, hint)| !hint.found);
+    let lines = hints
+        .into_iter()
+        .map(|(session, hint)| format!("[{}] {}", session.label(), session.hint_text(hint)))
+        .collect();
+
+    let block = Block::bordered().title(" Hints ");
+    let height = block.inner(hints_area).height;
+    frame.render_widget(tail(lines, height, scroll).block(block), hints_area);
+    frame.render_widget(Paragraph::new("Up/Down: scroll  Esc: back  Ctrl-C: quit"), help_area);
+}
@@ -0,0 +1,215 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use crate::alert::AlertKind;
+use crate::ap::address::ServerAddress;
+use crate::ap::connection::{
+    ITEMS_HANDLING_OTHER_WORLDS, ITEMS_HANDLING_OWN_WORLD, ITEMS_HANDLING_STARTING_INVENTORY,
+};
+use crate::context::Context;
+use crate::secrets::PasswordStorage;
+use crate::update::{Login, Message, Route};
+
+use super::{edit, Action};
+
+const LABEL_WIDTH: u16 = 28;
+
+/// Focused field of the login screen.
+#[derive(Default)]
+pub struct Form {
+    focus: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    PasswordStorage,
+    Passphrase,
+    Profile,
+    Name,
+    Slot,
+    Server,
+    Password,
+    Game,
+    Tags,
+    SlotData,
+    ItemsHandling(u32, &'static str),
+    AlertRule(AlertKind),
+}
+
+/// Fields in the order they are shown, the passphrase only while the
+/// secret store is locked.
+fn fields(context: &Context) -> Vec<Field> {
+    let mut fields = vec![Field::PasswordStorage];
+    if context.password_storage == PasswordStorage::Encrypted && !context.secrets.is_unlocked() {
+        fields.push(Field::Passphrase);
+    }
+    fields.extend([
+        Field::Profile,
+        Field::Name,
+        Field::Slot,
+        Field::Server,
+        Field::Password,
+        Field::Game,
+        Field::Tags,
+        Field::SlotData,
+        Field::ItemsHandling(ITEMS_HANDLING_OTHER_WORLDS, "Items from other worlds"),
+        Field::ItemsHandling(ITEMS_HANDLING_OWN_WORLD, "Items from own world"),
+        Field::ItemsHandling(ITEMS_HANDLING_STARTING_INVENTORY, "Starting inventory"),
+    ]);
+    fields.extend(AlertKind::ALL.map(Field::AlertRule));
+    fields
+}
+
+fn checkbox(checked: bool) -> String {
+    String::from(if checked { "[x]" } else { "[ ]" })
+}
+
+impl Form {
+    fn focused(&self, context: &Context) -> Field {
+        let fields = fields(context);
+        fields[self.focus.min(fields.len() - 1)]
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, login: &Login, context: &Context) -> Action {
+        let field_count = fields(context).len();
+        let field = self.focused(context);
+
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return match key.code {
+                KeyCode::Char('n') => Action::Dispatch(Message::NewProfile),
+                KeyCode::Char('d') => Action::Dispatch(Message::DuplicateProfile),
+                KeyCode::Char('x') => Action::Dispatch(Message::DeleteProfile),
+                _ => Action::None,
+            };
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::BackTab => {
+                self.focus = (self.focus.min(field_count - 1) + field_count - 1) % field_count;
+                return Action::None;
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.focus = (self.focus.min(field_count - 1) + 1) % field_count;
+                return Action::None;
+            }
+            KeyCode::Esc if context.has_sessions() => {
+                return Action::Dispatch(Message::Navigate(Route::Dashboard));
+            }
+            KeyCode::Esc => return Action::Quit,
+            _ => {}
+        }
+
+        let profile = context.profile();
+        let connection_info = &profile.connection_info;
+        let message = match (field, key.code) {
+            (Field::PasswordStorage, KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')) => {
+                Some(Message::PasswordStorageSelected(match context.password_storage {
+                    PasswordStorage::Encrypted => PasswordStorage::Never,
+                    PasswordStorage::Never => PasswordStorage::Encrypted,
+                }))
+            }
+            (Field::Passphrase, KeyCode::Enter) => Some(Message::UnlockSecrets),
+            (Field::Passphrase, _) => edit(&login.passphrase, key).map(Message::PassphraseInputChanged),
+            (Field::Profile, KeyCode::Left | KeyCode::Right) => {
+                let count = context.profiles.len();
+                let offset = if key.code == KeyCode::Left { count - 1 } else { 1 };
+                let choices = context.profile_choices();
+                Some(Message::ProfileSelected(choices[(context.selected_profile + offset) % count].clone()))
+            }
+            (Field::SlotData, KeyCode::Char(' ')) => Some(Message::SlotDataToggled(!connection_info.options.slot_data)),
+            (Field::ItemsHandling(flag, _), KeyCode::Char(' ')) => Some(Message::ItemsHandlingToggled(
+                flag,
+                connection_info.options.items_handling & flag == 0,
+            )),
+            (Field::AlertRule(kind), KeyCode::Char(' ')) => {
+                Some(Message::AlertRuleToggled(kind, !profile.alert_rules.get(kind)))
+            }
+            (_, KeyCode::Enter) => Some(Message::Connect),
+            (Field::Name, _) => edit(&profile.name, key).map(Message::ProfileNameInputChanged),
+            (Field::Slot, _) => edit(&connection_info.slot, key).map(Message::PseudoInputChanged),
+            (Field::Server, _) => edit(&connection_info.server, key).map(Message::ServerAddressInputChanged),
+            (Field::Password, _) => edit(&connection_info.password, key).map(Message::ServerPasswordInputChanged),
+            (Field::Game, _) => edit(&connection_info.options.game, key).map(Message::GameInputChanged),
+            (Field::Tags, _) => edit(&connection_info.options.tags.join(","), key).map(Message::TagsInputChanged),
+            _ => None,
+        };
+        message.map_or(Action::None, Action::Dispatch)
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect, login: &Login, context: &Context) {
+        let profile = context.profile();
+        let connection_info = &profile.connection_info;
+        let focused = self.focused(context);
+
+        let mut cursor = None;
+        let mut lines = Vec::new();
+        for field in fields(context) {
+            let (label, value, is_input) = match field {
+                Field::PasswordStorage => ("Password storage".to_owned(), format!("< {} >", context.password_storage), false),
+                Field::Passphrase if context.secrets.exists() => ("Passphrase".to_owned(), "*".repeat(login.passphrase.chars().count()), true),
+                Field::Passphrase => ("New passphrase".to_owned(), "*".repeat(login.passphrase.chars().count()), true),
+                Field::Profile => (
+                    "Profile".to_owned(),
+                    format!("< {} > ({}/{})", profile.name, context.selected_profile + 1, context.profiles.len()),
+                    false,
+                ),
+                Field::Name => ("Profile name".to_owned(), profile.name.clone(), true),
+                Field::Slot => ("Slot".to_owned(), connection_info.slot.clone(), true),
+                Field::Server => ("Server".to_owned(), connection_info.server.clone(), true),
+                Field::Password => ("Password".to_owned(), "*".repeat(connection_info.password.chars().count()), true),
+                Field::Game => ("Game".to_owned(), connection_info.options.game.clone(), true),
+                Field::Tags => ("Tags".to_owned(), connection_info.options.tags.join(","), true),
+                Field::SlotData => ("Request slot data".to_owned(), checkbox(connection_info.options.slot_data), false),
+                Field::ItemsHandling(flag, label) => (label.to_owned(), checkbox(connection_info.options.items_handling & flag != 0), false),
+                Field::AlertRule(kind) => (format!("Alert: {}", kind), checkbox(profile.alert_rules.get(kind)), false),
+            };
+
+            let style = if field == focused {
+                if is_input {
+                    cursor = Some((lines.len() as u16, LABEL_WIDTH + value.chars().count() as u16));
+                }
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::raw(format!("{:>width$}: ", label, width = LABEL_WIDTH as usize - 2)),
+                Span::styled(value, style),
+            ]));
+        }
+
+        let address_error = connection_info.server.parse::<ServerAddress>().err();
+        let status = match (&login.secrets_error, context.plaintext_passwords, address_error) {
+            (Some(err), _, _) => err.clone(),
+            (None, true, _) => String::from("Your config still holds passwords in clear text, set a passphrase to encrypt them"),
+            (None, false, Some(err)) => err.to_string(),
+            (None, false, None) => String::new(),
+        };
+
+        let [form_area, status_area, help_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let block = Block::bordered().title(" AP_Alert - Login ");
+        let inner = block.inner(form_area);
+        frame.render_widget(Paragraph::new(lines).block(block), form_area);
+        if let Some((y, x)) = cursor {
+            frame.set_cursor_position(Position::new(inner.x + x, inner.y + y));
+        }
+
+        frame.render_widget(Paragraph::new(status).style(Style::default().fg(Color::Red)), status_area);
+        frame.render_widget(
+            Paragraph::new(
+                "Up/Down: field  Left/Right/Space: change  Enter: connect  Ctrl-N/D/X: new/duplicate/delete profile  Esc: back  Ctrl-C: quit",
+            ),
+            help_area,
+        );
+    }
+}
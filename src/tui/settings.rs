@@ -0,0 +1,43 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use crate::api;
+use crate::context::Context;
+use crate::secrets::PasswordStorage;
+use crate::update::Message;
+
+use super::Action;
+
+pub fn handle_key(key: KeyEvent, context: &Context) -> Action {
+    match key.code {
+        KeyCode::Char('d') => Action::Dispatch(Message::DoNotDisturbToggled(!context.throttle_settings.do_not_disturb)),
+        KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') => {
+            Action::Dispatch(Message::PasswordStorageSelected(match context.password_storage {
+                PasswordStorage::Encrypted => PasswordStorage::Never,
+                PasswordStorage::Never => PasswordStorage::Encrypted,
+            }))
+        }
+        _ => Action::None,
+    }
+}
+
+pub fn draw(frame: &mut Frame, area: Rect, context: &Context) {
+    let [settings_area, help_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("Password storage: < {} >", context.password_storage)),
+            Line::from(format!(
+                "Do not disturb: {}",
+                if context.throttle_settings.do_not_disturb { "on" } else { "off" }
+            )),
+            Line::from(format!("Local API: {}", api::describe(context.api.as_ref()))),
+        ])
+            .block(Block::bordered().title(" Settings ")),
+        settings_area,
+    );
+    frame.render_widget(Paragraph::new("Left/Right/Space: change  d: do not disturb  Esc: back  Ctrl-C: quit"), help_area);
+}
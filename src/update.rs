@@ -0,0 +1,1111 @@
+//! Front end independent state and the reducer updating it.
+//!
+//! [`State::update`] only changes the state and lists the [`Effect`]s to
+//! run, [`State::run`] is the one place doing I/O. Front ends feed messages
+//! to [`State::dispatch`], which does both.
+
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::alert::AlertKind;
+use crate::appearance::{self, Density, PaletteColor};
+use crate::ap::address::ServerAddress;
+use crate::ap::connection::{self, ConnectionId, InputMessage};
+use crate::ap::messages::APServerMessage;
+use crate::context::Context;
+use crate::inbox;
+use crate::profile::ProfileChoice;
+use crate::secrets::PasswordStorage;
+use crate::session::Section;
+use crate::throttle;
+use crate::watchlist::{Syntax, Watch};
+
+/// Screens that can be navigated to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Route {
+    Login,
+    Dashboard,
+    Hints,
+    Alerts,
+    DataStorage,
+    Settings,
+}
+
+impl Route {
+    pub const ALL: [Route; 6] = [
+        Route::Login,
+        Route::Dashboard,
+        Route::Hints,
+        Route::Alerts,
+        Route::DataStorage,
+        Route::Settings,
+    ];
+}
+
+impl std::fmt::Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Route::Login => "Connect",
+            Route::Dashboard => "Dashboard",
+            Route::Hints => "Hints",
+            Route::Alerts => "Alerts",
+            Route::DataStorage => "Data storage",
+            Route::Settings => "Settings",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    PseudoInputChanged(String),
+    ServerAddressInputChanged(String),
+    ServerPasswordInputChanged(String),
+    GameInputChanged(String),
+    TagsInputChanged(String),
+    SlotDataToggled(bool),
+    ItemsHandlingToggled(u32, bool),
+    AlertRuleToggled(AlertKind, bool),
+    WatchAdded,
+    WatchRemoved(usize),
+    WatchPatternChanged(usize, String),
+    WatchSyntaxSelected(usize, Syntax),
+    WatchGameChanged(usize, String),
+    /// Comma separated, see [`Mentions`](crate::mention::Mentions).
+    MentionKeywordsChanged(String),
+    /// Comma separated slot names or aliases.
+    MutedPlayersChanged(String),
+    ProfileSelected(ProfileChoice),
+    ProfileNameInputChanged(String),
+    NewProfile,
+    DuplicateProfile,
+    DeleteProfile,
+    PassphraseInputChanged(String),
+    UnlockSecrets,
+    /// Result of [`Effect::UnlockSecrets`].
+    SecretsUnlocked(Result<(), String>),
+    PasswordStorageSelected(PasswordStorage),
+    #[allow(dead_code)]
+    Error(String),
+    Navigate(Route),
+    /// Goes back to the previous screen.
+    Back,
+    /// Closes every session and goes back to the login screen.
+    Disconnect,
+    WSEvent(ConnectionId, connection::Event),
+    Connect,
+    DashboardTabSelected(Option<ConnectionId>),
+    DashboardSectionSelected(Section),
+    ChatInputChanged(String),
+    SendChat,
+    CloseSession(ConnectionId),
+    StepReplay(ConnectionId),
+    DismissError(usize),
+    ThemeSelected(String),
+    PaletteColorChanged(PaletteColor, String),
+    /// Sent while dragging, the scale is saved on [`Message::TextScaleReleased`].
+    TextScaleChanged(f64),
+    TextScaleReleased,
+    DensitySelected(Density),
+    WindowResized { width: u32, height: u32 },
+    WindowMoved { x: i32, y: i32 },
+    WindowCloseRequested,
+    /// The window is about to close, with whether it is maximized.
+    WindowClosing(bool),
+    /// The terminal front end is about to exit.
+    Quit,
+    /// Switches between the full window and the alert overlay.
+    ToggleOverlay,
+    /// Answered by the GUI by letting the borderless overlay be dragged.
+    DragOverlay,
+    /// Sent while the overlay is shown, so that alerts fade out, and while
+    /// the throttle holds alerts back.
+    Tick(Instant),
+    OverlayAlertCountChanged(u8),
+    OverlayFadeTimeoutChanged(u32),
+    /// Saves the overlay settings once a slider is released.
+    OverlaySettingsReleased,
+    DoNotDisturbToggled(bool),
+    QuietHoursStartChanged(String),
+    QuietHoursEndChanged(String),
+    DataStorageTabSelected(ConnectionId),
+    DataStorageKeyInputChanged(String),
+    WatchKey,
+    UnwatchKey(String),
+    AlertReadToggled(ConnectionId, usize, bool),
+    MarkAllAlertsRead,
+    AcknowledgeAlert(ConnectionId, usize),
+    /// Snoozes an alert for this many minutes.
+    SnoozeAlert(ConnectionId, usize, u64),
+    SnoozeInputChanged(String),
+    /// Snoozes an alert until the time typed in the snooze input.
+    SnoozeAlertUntil(ConnectionId, usize),
+    ShowAcknowledgedToggled(bool),
+    /// Mutes the chat of a slot of a session.
+    PlayerMuteToggled(ConnectionId, u32, bool),
+    /// Shows the message that raised an alert in the log of its session.
+    JumpToAlert(ConnectionId, usize),
+}
+
+/// Side effects asked for by [`State::update`].
+#[derive(Debug)]
+pub enum Effect {
+    /// Writes the config and the secret store.
+    Save,
+    /// Hands a message to the worker of a session.
+    Send(ConnectionId, InputMessage),
+    UnlockSecrets(String),
+    DeleteSecrets,
+    /// Hands the room state and queued events to the local API and sinks.
+    Publish,
+    SaveInboxes,
+}
+
+#[derive(Debug, Default)]
+pub struct Login {
+    pub passphrase: String,
+    pub secrets_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    /// Session shown in the current tab, `None` for the combined tab.
+    pub selected: Option<ConnectionId>,
+    pub section: Section,
+    pub chat_input: String,
+    /// Log index of the message shown by [`Message::JumpToAlert`].
+    pub highlight: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Alerts {
+    /// Time to snooze alerts until, as `HH:MM`.
+    pub snooze_input: String,
+    pub show_acknowledged: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct DataStorage {
+    /// Session whose keys are shown, the first one when unset.
+    pub selected: Option<ConnectionId>,
+    pub key_input: String,
+}
+
+impl DataStorage {
+    /// The selected session if still running, else the first one.
+    pub fn session(&self, context: &Context) -> Option<ConnectionId> {
+        self.selected
+            .filter(|id| context.sessions.contains_key(id))
+            .or_else(|| context.sessions.keys().next().copied())
+    }
+}
+
+#[derive(Debug)]
+pub enum Screen {
+    Login(Login),
+    Dashboard(Dashboard),
+    Hints,
+    Alerts(Alerts),
+    DataStorage(DataStorage),
+    Settings,
+}
+
+impl Screen {
+    fn new(route: Route) -> Self {
+        match route {
+            Route::Login => Screen::Login(Login::default()),
+            Route::Dashboard => Screen::Dashboard(Dashboard::default()),
+            Route::Hints => Screen::Hints,
+            Route::Alerts => Screen::Alerts(Alerts::default()),
+            Route::DataStorage => Screen::DataStorage(DataStorage::default()),
+            Route::Settings => Screen::Settings,
+        }
+    }
+
+    pub fn route(&self) -> Route {
+        match self {
+            Screen::Login(_) => Route::Login,
+            Screen::Dashboard(_) => Route::Dashboard,
+            Screen::Hints => Route::Hints,
+            Screen::Alerts(_) => Route::Alerts,
+            Screen::DataStorage(_) => Route::DataStorage,
+            Screen::Settings => Route::Settings,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct State {
+    pub context: Context,
+    pub screen: Screen,
+    /// Screens left by navigating, most recent last.
+    pub history: Vec<Screen>,
+    /// Set while the window is the alert overlay.
+    pub overlay: bool,
+    /// Time of the last [`Message::Tick`], what alerts are faded against.
+    pub now: Instant,
+}
+
+impl State {
+    /// Starts on the screen left last time, the dashboard when it was the
+    /// login screen and sessions are already running, and the login screen
+    /// when a session is needed but none is.
+    pub fn new(context: Context) -> Self {
+        let route = match context.window.route {
+            Route::Settings => Route::Settings,
+            _ if !context.has_sessions() => Route::Login,
+            Route::Login => Route::Dashboard,
+            route => route,
+        };
+        let screen = match Screen::new(route) {
+            Screen::Dashboard(dashboard) => Screen::Dashboard(Dashboard {
+                section: context.window.section,
+                ..dashboard
+            }),
+            screen => screen,
+        };
+        Self {
+            context,
+            screen,
+            history: Vec::new(),
+            overlay: false,
+            now: Instant::now(),
+        }
+    }
+
+    /// Updates the state and runs the effects, until no message is left.
+    pub fn dispatch(&mut self, message: Message) {
+        let mut messages = vec![message];
+        while let Some(message) = messages.pop() {
+            for effect in self.update(message) {
+                messages.extend(self.run(effect));
+            }
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Vec<Effect> {
+        let mut effects = Vec::new();
+
+        match message {
+            Message::PseudoInputChanged(updated_pseudo) => {
+                self.context.profile_mut().connection_info.slot = updated_pseudo;
+            }
+            Message::ServerAddressInputChanged(updated_server) => {
+                self.context.profile_mut().connection_info.server = updated_server;
+            }
+            Message::ServerPasswordInputChanged(updated_password) => {
+                self.context.profile_mut().connection_info.password = updated_password;
+            }
+            Message::GameInputChanged(updated_game) => {
+                self.context.profile_mut().connection_info.options.game = updated_game;
+            }
+            Message::TagsInputChanged(updated_tags) => {
+                // Kept as typed, empty tags are dropped when connecting.
+                self.context.profile_mut().connection_info.options.tags =
+                    updated_tags.split(',').map(str::to_owned).collect();
+            }
+            Message::SlotDataToggled(enabled) => {
+                self.context.profile_mut().connection_info.options.slot_data = enabled;
+            }
+            Message::ItemsHandlingToggled(flag, enabled) => {
+                let items_handling = &mut self.context.profile_mut().connection_info.options.items_handling;
+                if enabled {
+                    *items_handling |= flag;
+                } else {
+                    *items_handling &= !flag;
+                }
+            }
+            Message::AlertRuleToggled(kind, enabled) => {
+                self.context.profile_mut().alert_rules.set(kind, enabled);
+            }
+            Message::WatchAdded => {
+                self.context.profile_mut().watchlist.push(Watch::default());
+                self.context.apply_watchlist();
+            }
+            Message::WatchRemoved(index) => {
+                let watchlist = &mut self.context.profile_mut().watchlist;
+                if index < watchlist.len() {
+                    watchlist.remove(index);
+                }
+                self.context.apply_watchlist();
+            }
+            Message::WatchPatternChanged(index, pattern) => {
+                if let Some(watch) = self.context.profile_mut().watchlist.get_mut(index) {
+                    watch.pattern = pattern;
+                }
+                self.context.apply_watchlist();
+            }
+            Message::WatchSyntaxSelected(index, syntax) => {
+                if let Some(watch) = self.context.profile_mut().watchlist.get_mut(index) {
+                    watch.syntax = syntax;
+                }
+                self.context.apply_watchlist();
+            }
+            Message::WatchGameChanged(index, game) => {
+                if let Some(watch) = self.context.profile_mut().watchlist.get_mut(index) {
+                    watch.game = game;
+                }
+                self.context.apply_watchlist();
+            }
+            Message::MentionKeywordsChanged(keywords) => {
+                self.context.profile_mut().mentions.keywords = keywords.split(',').map(str::to_owned).collect();
+            }
+            Message::MutedPlayersChanged(muted) => {
+                self.context.profile_mut().mentions.muted = muted.split(',').map(str::to_owned).collect();
+            }
+
+            Message::ProfileSelected(choice) => self.context.select_profile(choice.index),
+            Message::ProfileNameInputChanged(updated_name) => {
+                self.context.profile_mut().name = updated_name;
+            }
+            Message::NewProfile => {
+                self.context.new_profile();
+                effects.push(Effect::Save);
+            }
+            Message::DuplicateProfile => {
+                self.context.duplicate_profile();
+                effects.push(Effect::Save);
+            }
+            Message::DeleteProfile => {
+                self.context.delete_profile();
+                effects.push(Effect::Save);
+            }
+
+            Message::PassphraseInputChanged(updated_passphrase) => {
+                if let Screen::Login(login) = &mut self.screen {
+                    login.passphrase = updated_passphrase;
+                }
+            }
+            Message::UnlockSecrets => {
+                if let Screen::Login(login) = &mut self.screen {
+                    effects.push(Effect::UnlockSecrets(std::mem::take(&mut login.passphrase)));
+                }
+            }
+            Message::SecretsUnlocked(result) => {
+                if let Screen::Login(login) = &mut self.screen {
+                    login.secrets_error = result.err();
+                }
+            }
+            Message::PasswordStorageSelected(password_storage) => {
+                self.context.password_storage = password_storage;
+                if password_storage == PasswordStorage::Never {
+                    self.context.plaintext_passwords = false;
+                    effects.push(Effect::DeleteSecrets);
+                }
+                effects.push(Effect::Save);
+                if let Screen::Login(login) = &mut self.screen {
+                    login.secrets_error = None;
+                }
+            }
+
+            Message::ThemeSelected(theme) => {
+                self.context.appearance.theme = theme;
+                effects.push(Effect::Save);
+            }
+            Message::PaletteColorChanged(color, input) => {
+                let valid = appearance::parse_color(&input).is_some();
+                *self.context.appearance.palette.get_mut(color) = input;
+                if valid {
+                    effects.push(Effect::Save);
+                }
+            }
+            Message::TextScaleChanged(scale) => self.context.appearance.text_scale = scale,
+            Message::TextScaleReleased => effects.push(Effect::Save),
+            Message::DensitySelected(density) => {
+                self.context.appearance.density = density;
+                effects.push(Effect::Save);
+            }
+
+            Message::WindowResized { width, height } if self.overlay => {
+                self.context.overlay.width = width;
+                self.context.overlay.height = height;
+            }
+            Message::WindowResized { width, height } => {
+                self.context.window.width = width;
+                self.context.window.height = height;
+            }
+            Message::WindowMoved { x, y } if self.overlay => self.context.overlay.position = Some((x, y)),
+            Message::WindowMoved { x, y } => self.context.window.position = Some((x, y)),
+            // Answered by the GUI with whether the window is maximized.
+            Message::WindowCloseRequested => {}
+            Message::WindowClosing(maximized) => {
+                if !self.overlay {
+                    self.context.window.maximized = maximized;
+                }
+                self.remember_screen();
+                effects.push(Effect::Save);
+            }
+            Message::Quit => {
+                self.remember_screen();
+                effects.push(Effect::Save);
+            }
+
+            Message::ToggleOverlay => {
+                self.overlay = !self.overlay;
+                effects.push(Effect::Save);
+            }
+            Message::DragOverlay => {}
+            Message::Tick(now) => {
+                self.now = now;
+                if !self.context.tick(now).is_empty() && self.context.is_publishing() {
+                    effects.push(Effect::Publish);
+                }
+            }
+            Message::OverlayAlertCountChanged(count) => self.context.overlay.alert_count = count,
+            Message::OverlayFadeTimeoutChanged(timeout) => self.context.overlay.fade_timeout = timeout,
+            Message::OverlaySettingsReleased => effects.push(Effect::Save),
+            Message::DoNotDisturbToggled(enabled) => {
+                self.context.throttle_settings.do_not_disturb = enabled;
+                effects.push(Effect::Save);
+            }
+            Message::QuietHoursStartChanged(input) => {
+                if input.is_empty() || throttle::parse_time(&input).is_some() {
+                    effects.push(Effect::Save);
+                }
+                self.context.throttle_settings.quiet_hours.start = input;
+            }
+            Message::QuietHoursEndChanged(input) => {
+                if input.is_empty() || throttle::parse_time(&input).is_some() {
+                    effects.push(Effect::Save);
+                }
+                self.context.throttle_settings.quiet_hours.end = input;
+            }
+
+            Message::Error(err) => self.context.report(anyhow::anyhow!(err)),
+            Message::DismissError(index) => {
+                if index < self.context.errors.len() {
+                    self.context.errors.remove(index);
+                }
+            }
+            Message::Navigate(route) => {
+                if route != self.screen.route() {
+                    let left = std::mem::replace(&mut self.screen, Screen::new(route));
+                    self.history.push(left);
+                }
+            }
+            Message::Back => {
+                if let Some(screen) = self.history.pop() {
+                    self.screen = screen;
+                }
+            }
+            Message::Disconnect => {
+                let ids: Vec<_> = self.context.sessions.keys().copied().collect();
+                for id in ids {
+                    self.context.close_session(id);
+                }
+                self.reset(Route::Login);
+            }
+
+            Message::Connect => {
+                let server = &self.context.profile().connection_info.server;
+                if server.parse::<ServerAddress>().is_ok() {
+                    info!("attempting connexion");
+                    self.context.touch_profile();
+                    self.context.start_session();
+                }
+            }
+            Message::WSEvent(id, event) => {
+                self.context.process(id, &event);
+                if self.context.is_publishing() {
+                    effects.push(Effect::Publish);
+                }
+
+                let connected = matches!(event, connection::Event::APMessage(APServerMessage::Connected(_)));
+                if connected && matches!(self.screen, Screen::Login(_)) {
+                    info!("Logged in");
+                    effects.push(Effect::Save);
+                    self.reset(Route::Dashboard);
+                }
+            }
+            Message::CloseSession(id) => {
+                self.context.close_session(id);
+                if let Screen::Dashboard(dashboard) = &mut self.screen {
+                    if dashboard.selected == Some(id) {
+                        dashboard.selected = None;
+                    }
+                }
+                if !self.context.has_sessions() {
+                    self.reset(Route::Login);
+                }
+            }
+            Message::StepReplay(id) => {
+                if let Some(session) = self.context.sessions.get_mut(&id) {
+                    session.step();
+                }
+            }
+
+            Message::DashboardTabSelected(selected) => {
+                if let Screen::Dashboard(dashboard) = &mut self.screen {
+                    dashboard.selected = selected;
+                    dashboard.highlight = None;
+                }
+            }
+            Message::DashboardSectionSelected(section) => {
+                if let Screen::Dashboard(dashboard) = &mut self.screen {
+                    dashboard.section = section;
+                    dashboard.highlight = None;
+                }
+            }
+            Message::ChatInputChanged(input) => {
+                if let Screen::Dashboard(dashboard) = &mut self.screen {
+                    dashboard.chat_input = input;
+                }
+            }
+            Message::SendChat => {
+                if let Screen::Dashboard(dashboard) = &mut self.screen {
+                    let session = dashboard.selected.and_then(|id| self.context.sessions.get_mut(&id));
+                    if let Some(session) = session {
+                        if session.room.is_some() && !dashboard.chat_input.is_empty() {
+                            session.say(std::mem::take(&mut dashboard.chat_input));
+                        }
+                    }
+                }
+            }
+
+            Message::AlertReadToggled(id, index, read) => {
+                if let Some(session) = self.context.sessions.get_mut(&id) {
+                    session.inbox.update(index, |entry| entry.read = read);
+                }
+            }
+            Message::MarkAllAlertsRead => {
+                for session in self.context.sessions.values_mut() {
+                    session.inbox.mark_all_read();
+                }
+            }
+            Message::AcknowledgeAlert(id, index) => {
+                if let Some(session) = self.context.sessions.get_mut(&id) {
+                    session.inbox.update(index, |entry| {
+                        entry.acknowledged = true;
+                        entry.read = true;
+                    });
+                }
+            }
+            Message::SnoozeAlert(id, index, minutes) => {
+                if let Some(session) = self.context.sessions.get_mut(&id) {
+                    session.inbox.snooze(index, inbox::now() + minutes * 60);
+                }
+            }
+            Message::SnoozeInputChanged(input) => {
+                if let Screen::Alerts(alerts) = &mut self.screen {
+                    alerts.snooze_input = input;
+                }
+            }
+            Message::SnoozeAlertUntil(id, index) => {
+                if let Screen::Alerts(alerts) = &self.screen {
+                    let until = inbox::snooze_until(&alerts.snooze_input);
+                    let session = self.context.sessions.get_mut(&id);
+                    if let (Some(session), Some(until)) = (session, until) {
+                        session.inbox.snooze(index, until);
+                    }
+                }
+            }
+            Message::ShowAcknowledgedToggled(show) => {
+                if let Screen::Alerts(alerts) = &mut self.screen {
+                    alerts.show_acknowledged = show;
+                }
+            }
+            Message::JumpToAlert(id, index) => {
+                let session = self.context.sessions.get_mut(&id);
+                if let Some(session) = session {
+                    session.inbox.update(index, |entry| entry.read = true);
+                    let log_index = session.inbox.entries.get(index).and_then(|entry| entry.log_index);
+                    let dashboard = Dashboard {
+                        selected: Some(id),
+                        section: Section::Log,
+                        highlight: log_index,
+                        ..Default::default()
+                    };
+                    let left = std::mem::replace(&mut self.screen, Screen::Dashboard(dashboard));
+                    self.history.push(left);
+                }
+            }
+
+            Message::PlayerMuteToggled(id, slot, muted) => {
+                if self.context.mute_player(id, slot, muted) {
+                    effects.push(Effect::Save);
+                }
+            }
+
+            Message::DataStorageTabSelected(id) => {
+                if let Screen::DataStorage(data_storage) = &mut self.screen {
+                    data_storage.selected = Some(id);
+                }
+            }
+            Message::DataStorageKeyInputChanged(input) => {
+                if let Screen::DataStorage(data_storage) = &mut self.screen {
+                    data_storage.key_input = input;
+                }
+            }
+            Message::WatchKey => {
+                if let Screen::DataStorage(data_storage) = &mut self.screen {
+                    let key = data_storage.key_input.trim().to_owned();
+                    let session = data_storage
+                        .session(&self.context)
+                        .and_then(|id| self.context.sessions.get_mut(&id));
+                    if let Some(session) = session.filter(|_| !key.is_empty()) {
+                        session.watch(key);
+                        data_storage.key_input.clear();
+                    }
+                }
+            }
+            Message::UnwatchKey(key) => {
+                if let Screen::DataStorage(data_storage) = &mut self.screen {
+                    let session = data_storage
+                        .session(&self.context)
+                        .and_then(|id| self.context.sessions.get_mut(&id));
+                    if let Some(session) = session {
+                        session.unwatch(&key);
+                    }
+                }
+            }
+        }
+
+        if self.context.inboxes_changed() {
+            effects.push(Effect::SaveInboxes);
+        }
+        effects.extend(
+            self.context
+                .take_outgoing()
+                .into_iter()
+                .map(|(id, message)| Effect::Send(id, message)),
+        );
+        effects
+    }
+
+    /// Runs an effect, returning the message carrying its result if any.
+    pub fn run(&mut self, effect: Effect) -> Option<Message> {
+        match effect {
+            Effect::Save => self.context.save(),
+            Effect::Send(id, message) => self.context.deliver(id, message),
+            Effect::UnlockSecrets(passphrase) => {
+                let result = self.context.unlock_secrets(&passphrase);
+                return Some(Message::SecretsUnlocked(result.map_err(|err| err.to_string())));
+            }
+            Effect::DeleteSecrets => {
+                if let Err(err) = self.context.secrets.delete() {
+                    self.context.report(err);
+                }
+            }
+            Effect::Publish => self.context.publish(),
+            Effect::SaveInboxes => self.context.save_inboxes(),
+        }
+        None
+    }
+
+    /// Keeps the current screen to be shown again on the next start.
+    fn remember_screen(&mut self) {
+        let window = &mut self.context.window;
+        window.route = self.screen.route();
+        if let Screen::Dashboard(dashboard) = &self.screen {
+            window.section = dashboard.section;
+        }
+    }
+
+    /// Shows a screen, forgetting the ones navigated from.
+    fn reset(&mut self, route: Route) {
+        self.history.clear();
+        self.screen = Screen::new(route);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::futures::channel::mpsc;
+    use serde_json::json;
+
+    use super::*;
+    use crate::ap::connection::{Connection, Event, ITEMS_HANDLING_STARTING_INVENTORY};
+
+    fn state() -> State {
+        State::new(Context::default())
+    }
+
+    /// A state with a session for `archipelago.gg:38281` whose worker is
+    /// ready.
+    fn connecting() -> (State, ConnectionId) {
+        let mut state = state();
+        state.update(Message::ServerAddressInputChanged(String::from("archipelago.gg:38281")));
+        state.update(Message::Connect);
+        let id = *state.context.sessions.keys().next().unwrap();
+
+        let (sender, _receiver) = mpsc::channel(10);
+        state.update(Message::WSEvent(id, Event::WorkerReady(Connection(sender))));
+        (state, id)
+    }
+
+    fn connected() -> Event {
+        let message = json!({
+            "cmd": "Connected",
+            "team": 0,
+            "slot": 1,
+            "players": [
+                { "team": 0, "slot": 1, "alias": "Player1", "name": "Player1" },
+                { "team": 0, "slot": 2, "alias": "Bob", "name": "Bob" },
+            ],
+            "missing_locations": [],
+            "checked_locations": [],
+            "hint_points": 0,
+            "slot_info": {
+                "1": { "name": "Player1", "game": "Clique", "type": 1, "group_members": [] },
+                "2": { "name": "Bob", "game": "Clique", "type": 1, "group_members": [] },
+            },
+        });
+        Event::APMessage(serde_json::from_value(message).unwrap())
+    }
+
+    #[test]
+    fn edits_the_selected_profile() {
+        let mut state = state();
+        assert!(state.update(Message::PseudoInputChanged(String::from("Alice"))).is_empty());
+        state.update(Message::TagsInputChanged(String::from("Tracker,")));
+        state.update(Message::ItemsHandlingToggled(ITEMS_HANDLING_STARTING_INVENTORY, true));
+
+        let connection_info = &state.context.profile().connection_info;
+        assert_eq!(connection_info.slot, "Alice");
+        assert_eq!(connection_info.options.tags, ["Tracker", ""]);
+        assert_ne!(connection_info.options.items_handling & ITEMS_HANDLING_STARTING_INVENTORY, 0);
+    }
+
+    #[test]
+    fn saves_profile_changes() {
+        let mut state = state();
+        let effects = state.update(Message::NewProfile);
+        assert!(matches!(effects[..], [Effect::Save]));
+        assert_eq!(state.context.profiles.len(), 2);
+    }
+
+    #[test]
+    fn unlocks_with_the_typed_passphrase() {
+        let mut state = state();
+        state.update(Message::PassphraseInputChanged(String::from("hunter2")));
+        let effects = state.update(Message::UnlockSecrets);
+        assert!(matches!(&effects[..], [Effect::UnlockSecrets(passphrase)] if passphrase == "hunter2"));
+
+        state.update(Message::SecretsUnlocked(Err(String::from("wrong passphrase"))));
+        let Screen::Login(login) = &state.screen else {
+            panic!("left the login page");
+        };
+        assert!(login.passphrase.is_empty());
+        assert_eq!(login.secrets_error.as_deref(), Some("wrong passphrase"));
+    }
+
+    #[test]
+    fn never_storing_passwords_deletes_them() {
+        let mut state = state();
+        let effects = state.update(Message::PasswordStorageSelected(PasswordStorage::Never));
+        assert!(matches!(effects[..], [Effect::DeleteSecrets, Effect::Save]));
+    }
+
+    #[test]
+    fn does_not_connect_to_an_invalid_address() {
+        let mut state = state();
+        state.update(Message::ServerAddressInputChanged(String::from("not an address:port")));
+        assert!(state.update(Message::Connect).is_empty());
+        assert!(state.context.sessions.is_empty());
+    }
+
+    #[test]
+    fn connects_once_the_worker_is_ready() {
+        let mut state = state();
+        state.update(Message::ServerAddressInputChanged(String::from("archipelago.gg:38281")));
+        assert!(state.update(Message::Connect).is_empty());
+        let id = *state.context.sessions.keys().next().unwrap();
+
+        let (sender, _receiver) = mpsc::channel(10);
+        let effects = state.update(Message::WSEvent(id, Event::WorkerReady(Connection(sender))));
+        assert!(matches!(&effects[..], [Effect::Send(to, InputMessage::Connect(request))]
+            if *to == id && request.server == "archipelago.gg:38281"));
+    }
+
+    #[test]
+    fn shows_the_dashboard_once_logged_in() {
+        let (mut state, id) = connecting();
+        let effects = state.update(Message::WSEvent(id, connected()));
+
+        assert!(matches!(effects[0], Effect::Save));
+        assert!(matches!(state.screen, Screen::Dashboard(_)));
+        assert!(state.context.sessions[&id].room.is_some());
+    }
+
+    #[test]
+    fn sends_chat_from_the_selected_tab() {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        state.update(Message::DashboardTabSelected(Some(id)));
+        state.update(Message::ChatInputChanged(String::from("hello")));
+
+        let effects = state.update(Message::SendChat);
+        assert!(matches!(&effects[..], [Effect::Send(to, InputMessage::Send(_))] if *to == id));
+        let Screen::Dashboard(dashboard) = &state.screen else {
+            panic!("left the dashboard");
+        };
+        assert!(dashboard.chat_input.is_empty());
+    }
+
+    #[test]
+    fn closing_the_last_session_goes_back_to_login() {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        state.update(Message::DashboardTabSelected(Some(id)));
+
+        let effects = state.update(Message::CloseSession(id));
+        assert!(matches!(&effects[..], [Effect::Send(to, InputMessage::Disconnect)] if *to == id));
+        assert!(matches!(state.screen, Screen::Login(_)));
+
+        // Dropped once the worker closed the socket.
+        assert!(state.context.sessions.contains_key(&id));
+        state.update(Message::WSEvent(id, Event::Disconnected));
+        assert!(state.context.sessions.is_empty());
+    }
+
+    #[test]
+    fn goes_back_to_the_previous_screen() {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        state.update(Message::DashboardSectionSelected(Section::Chat));
+
+        state.update(Message::Navigate(Route::Hints));
+        state.update(Message::Navigate(Route::Hints));
+        state.update(Message::Navigate(Route::Settings));
+        assert_eq!(state.history.len(), 2);
+
+        state.update(Message::Back);
+        assert_eq!(state.screen.route(), Route::Hints);
+        state.update(Message::Back);
+        let Screen::Dashboard(dashboard) = &state.screen else {
+            panic!("not back on the dashboard");
+        };
+        assert_eq!(dashboard.section, Section::Chat);
+    }
+
+    #[test]
+    fn disconnect_closes_every_session() {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        state.update(Message::Navigate(Route::Alerts));
+
+        let effects = state.update(Message::Disconnect);
+        assert!(matches!(&effects[..], [Effect::Send(_, InputMessage::Disconnect)]));
+        assert!(matches!(state.screen, Screen::Login(_)));
+        assert!(state.history.is_empty());
+        assert!(!state.context.has_sessions());
+    }
+
+    #[test]
+    fn watches_data_storage_keys() {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        state.update(Message::Navigate(Route::DataStorage));
+        state.update(Message::DataStorageKeyInputChanged(String::from("_read_hints_0_1")));
+
+        let effects = state.update(Message::WatchKey);
+        assert!(matches!(&effects[..], [Effect::Send(to, InputMessage::Send(messages))]
+            if *to == id && messages.len() == 2));
+        assert!(state.context.sessions[&id].data_storage.contains_key("_read_hints_0_1"));
+    }
+
+    #[test]
+    fn saves_palette_colors_once_valid() {
+        let mut state = state();
+        state.update(Message::ThemeSelected(String::from(appearance::CUSTOM_THEME)));
+
+        let effects = state.update(Message::PaletteColorChanged(PaletteColor::Primary, String::from("#12")));
+        assert!(effects.is_empty());
+        let effects = state.update(Message::PaletteColorChanged(PaletteColor::Primary, String::from("#123456")));
+        assert!(matches!(effects[..], [Effect::Save]));
+
+        let theme = state.context.appearance.theme();
+        assert_eq!(theme.palette().primary, iced::Color::from_rgb8(0x12, 0x34, 0x56));
+    }
+
+    #[test]
+    fn restores_the_last_screen() {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        state.update(Message::DashboardSectionSelected(Section::Hints));
+        state.update(Message::WindowResized { width: 800, height: 600 });
+        let effects = state.update(Message::WindowClosing(true));
+        assert!(matches!(effects[..], [Effect::Save]));
+
+        let window = state.context.window.clone();
+        assert_eq!((window.width, window.height, window.maximized), (800, 600, true));
+        assert_eq!((window.route, window.section), (Route::Dashboard, Section::Hints));
+
+        // Without sessions the dashboard would be empty.
+        let mut context = Context::default();
+        context.window = window;
+        assert_eq!(State::new(context).screen.route(), Route::Login);
+
+        let restored = State::new(state.context);
+        let Screen::Dashboard(dashboard) = &restored.screen else {
+            panic!("not restored on the dashboard");
+        };
+        assert_eq!(dashboard.section, Section::Hints);
+    }
+
+    #[test]
+    fn jumps_from_an_alert_to_its_message() {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        let item_send = json!({
+            "cmd": "PrintJSON",
+            "type": "ItemSend",
+            "data": [{ "text": "Hookshot" }],
+            "receiving": 1,
+            "item": { "item": 1, "location": 2, "player": 1, "flags": 0 },
+        });
+        state.update(Message::WSEvent(id, Event::APMessage(serde_json::from_value(item_send).unwrap())));
+        state.update(Message::Navigate(Route::Alerts));
+        state.update(Message::AcknowledgeAlert(id, 0));
+        let entry = &state.context.sessions[&id].inbox.entries[0];
+        assert!(entry.read && entry.acknowledged);
+        assert_eq!(entry.message.as_ref().map(|message| message.text.as_str()), Some("Hookshot"));
+
+        state.update(Message::JumpToAlert(id, 0));
+        let Screen::Dashboard(dashboard) = &state.screen else {
+            panic!("not on the dashboard");
+        };
+        assert_eq!((dashboard.selected, dashboard.section, dashboard.highlight), (Some(id), Section::Log, Some(0)));
+        assert_eq!(state.context.sessions[&id].log_position(0), Some(0));
+    }
+
+    fn print(id: ConnectionId, print: serde_json::Value) -> Message {
+        Message::WSEvent(id, Event::APMessage(serde_json::from_value(print).unwrap()))
+    }
+
+    /// A logged in session knowing the names of Clique.
+    fn knowing_names() -> (State, ConnectionId) {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        let data_package = json!({
+            "cmd": "DataPackage",
+            "data": { "games": { "Clique": {
+                "item_name_to_id": { "Button Activation": 1 },
+                "location_name_to_id": { "The Big Red Button": 2 },
+            } } },
+        });
+        state.update(print(id, data_package));
+        (state, id)
+    }
+
+    /// Button Activation, found by `finder` at location `location` for
+    /// `receiving`.
+    fn item_send(receiving: u32, finder: u32, location: u64) -> serde_json::Value {
+        json!({
+            "cmd": "PrintJSON",
+            "type": "ItemSend",
+            "data": [{ "type": "item_id", "text": "1", "player": receiving }],
+            "receiving": receiving,
+            "item": { "item": 1, "location": location, "player": finder, "flags": 0 },
+        })
+    }
+
+    fn watch_buttons(state: &mut State) {
+        state.update(Message::WatchAdded);
+        state.update(Message::WatchPatternChanged(0, String::from("button*")));
+        state.update(Message::WatchSyntaxSelected(0, Syntax::Glob));
+    }
+
+    fn alert_kinds(state: &State, id: ConnectionId) -> Vec<AlertKind> {
+        state.context.sessions[&id].inbox.entries.iter().map(|entry| entry.kind).collect()
+    }
+
+    #[test]
+    fn applies_watchlist_edits_to_a_running_session() {
+        let (mut state, id) = knowing_names();
+        state.update(print(id, item_send(1, 2, 2)));
+
+        watch_buttons(&mut state);
+        state.update(print(id, item_send(1, 2, 3)));
+        assert_eq!(alert_kinds(&state, id), [AlertKind::ItemReceived, AlertKind::Watched]);
+        assert!(state.context.sessions[&id].highlighted_log().all(|(_, flagged)| flagged));
+
+        state.update(Message::WatchRemoved(0));
+        state.update(print(id, item_send(1, 2, 4)));
+        assert_eq!(alert_kinds(&state, id)[2], AlertKind::ItemReceived);
+        assert!(state.context.sessions[&id].highlighted_log().all(|(_, flagged)| !flagged));
+    }
+
+    #[test]
+    fn alerts_on_watched_items_hinted() {
+        let (mut state, id) = knowing_names();
+        watch_buttons(&mut state);
+        let hint = json!({
+            "cmd": "PrintJSON",
+            "type": "Hint",
+            "data": [{ "type": "item_id", "text": "1", "player": 1 }],
+            "receiving": 1,
+            "item": { "item": 1, "location": 2, "player": 2, "flags": 0 },
+            "found": false,
+        });
+        state.update(print(id, hint));
+        assert_eq!(alert_kinds(&state, id), [AlertKind::Watched]);
+    }
+
+    #[test]
+    fn alerts_on_watched_items_found_in_the_slot_world() {
+        let (mut state, id) = knowing_names();
+        watch_buttons(&mut state);
+        state.update(print(id, item_send(2, 1, 2)));
+        assert_eq!(alert_kinds(&state, id), [AlertKind::Watched]);
+    }
+
+    #[test]
+    fn alerts_on_chat_mentions_unless_muted() {
+        let (mut state, id) = connecting();
+        state.update(Message::WSEvent(id, connected()));
+        let chat = |message: &str| {
+            let chat = json!({
+                "cmd": "PrintJSON",
+                "type": "Chat",
+                "data": [{ "text": format!("Bob: {}", message) }],
+                "team": 0,
+                "slot": 2,
+                "message": message,
+            });
+            Message::WSEvent(id, Event::APMessage(serde_json::from_value(chat).unwrap()))
+        };
+        state.update(chat("gg @player1"));
+        state.update(chat("Player10 is someone else"));
+        let kinds: Vec<_> = state.context.sessions[&id].inbox.entries.iter().map(|entry| entry.kind).collect();
+        assert_eq!(kinds, [AlertKind::Mention]);
+
+        let effects = state.update(Message::PlayerMuteToggled(id, 2, true));
+        assert!(matches!(effects[..], [Effect::Save]));
+        assert_eq!(state.context.profile().mentions.muted, [String::from("Bob")]);
+        state.update(chat("Player1 look"));
+        let session = &state.context.sessions[&id];
+        assert_eq!(session.inbox.entries.len(), 1);
+        assert_eq!(session.chat().count(), 0);
+    }
+
+    #[test]
+    fn saves_the_login_form_when_quitting() {
+        let mut state = state();
+        state.update(Message::ProfileNameInputChanged(String::from("Async")));
+        state.update(Message::AlertRuleToggled(AlertKind::Hint, false));
+        let effects = state.update(Message::Quit);
+        assert!(matches!(effects[..], [Effect::Save]));
+        assert_eq!(state.context.window.route, Route::Login);
+    }
+
+    #[test]
+    fn overlay_keeps_its_own_geometry() {
+        let mut state = state();
+        state.update(Message::WindowResized { width: 800, height: 600 });
+        let effects = state.update(Message::ToggleOverlay);
+        assert!(matches!(effects[..], [Effect::Save]));
+
+        state.update(Message::WindowResized { width: 300, height: 200 });
+        state.update(Message::WindowMoved { x: 10, y: 20 });
+        state.update(Message::WindowClosing(false));
+        assert_eq!((state.context.overlay.width, state.context.overlay.height), (300, 200));
+        assert_eq!(state.context.overlay.position, Some((10, 20)));
+        assert_eq!((state.context.window.width, state.context.window.height), (800, 600));
+        assert_eq!(state.context.window.position, None);
+
+        state.update(Message::ToggleOverlay);
+        assert!(!state.overlay);
+    }
+}
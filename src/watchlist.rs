@@ -0,0 +1,103 @@
+//! Items of a profile worth a high priority alert, matched by name against
+//! the DataPackage names of their game.
+
+mod pattern;
+
+use serde::{Deserialize, Serialize};
+
+pub use pattern::Pattern;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Syntax {
+    #[default]
+    Exact,
+    Glob,
+    Regex,
+}
+
+impl Syntax {
+    pub const ALL: [Syntax; 3] = [Syntax::Exact, Syntax::Glob, Syntax::Regex];
+}
+
+impl std::fmt::Display for Syntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Syntax::Exact => "Exact",
+            Syntax::Glob => "Glob",
+            Syntax::Regex => "Regex",
+        })
+    }
+}
+
+/// An entry of the watchlist of a profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watch {
+    pub pattern: String,
+    #[serde(default)]
+    pub syntax: Syntax,
+    /// Game of the items, empty for any game.
+    #[serde(default)]
+    pub game: String,
+}
+
+impl Watch {
+    pub fn compile(&self) -> Result<Pattern, String> {
+        match self.syntax {
+            Syntax::Exact => Ok(Pattern::exact(&self.pattern)),
+            Syntax::Glob => Ok(Pattern::glob(&self.pattern)),
+            Syntax::Regex => Pattern::regex(&self.pattern),
+        }
+    }
+}
+
+/// The compiled watchlist of a session, invalid and empty patterns left out.
+#[derive(Debug, Default)]
+pub struct Watchlist {
+    /// Lowercased game, empty for any game, and pattern.
+    patterns: Vec<(String, Pattern)>,
+}
+
+impl Watchlist {
+    pub fn new(watches: &[Watch]) -> Self {
+        let patterns = watches
+            .iter()
+            .filter(|watch| !watch.pattern.trim().is_empty())
+            .filter_map(|watch| Some((watch.game.trim().to_lowercase(), watch.compile().ok()?)))
+            .collect();
+        Self { patterns }
+    }
+
+    /// Whether the item named `item` of `game` is watched.
+    pub fn contains(&self, game: &str, item: &str) -> bool {
+        let game = game.to_lowercase();
+        self.patterns
+            .iter()
+            .any(|(watched, pattern)| (watched.is_empty() || *watched == game) && pattern.is_match(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_patterns_to_their_game() {
+        let watch = |pattern: &str, syntax, game: &str| Watch {
+            pattern: pattern.to_owned(),
+            syntax,
+            game: game.to_owned(),
+        };
+        let watchlist = Watchlist::new(&[
+            watch("Hookshot", Syntax::Exact, "Ocarina of Time"),
+            watch("*Key*", Syntax::Glob, ""),
+            watch("(", Syntax::Regex, ""),
+            watch(" ", Syntax::Glob, ""),
+        ]);
+
+        assert!(watchlist.contains("ocarina of time", "Hookshot"));
+        assert!(!watchlist.contains("A Link to the Past", "Hookshot"));
+        assert!(watchlist.contains("A Link to the Past", "Big Key (Eastern Palace)"));
+        assert!(!watchlist.contains("A Link to the Past", "Bow"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Case insensitive item name patterns: exact names, globs with `*` and `?`,
+//! and regexes, which match anywhere in the name unless anchored. All of
+//! them compile to a [`Regex`], matching in linear time whatever the
+//! pattern typed.
+
+use regex::{Regex, RegexBuilder};
+
+/// A compiled pattern.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn exact(pattern: &str) -> Self {
+        Self::anchored(&regex::escape(pattern.trim()))
+    }
+
+    /// A glob matching the whole name.
+    pub fn glob(pattern: &str) -> Self {
+        let translated: String = pattern
+            .trim()
+            .chars()
+            .map(|c| match c {
+                '*' => String::from(".*"),
+                '?' => String::from("."),
+                c => regex::escape(c.encode_utf8(&mut [0; 4])),
+            })
+            .collect();
+        Self::anchored(&translated)
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self, String> {
+        Self::build(pattern).map(Self).map_err(|err| err.to_string())
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.0.is_match(name)
+    }
+
+    /// Compiles `pattern`, made of escaped text and `.*` or `.` only, to
+    /// match the whole name.
+    fn anchored(pattern: &str) -> Self {
+        let regex = Self::build(&format!("^(?:{})$", pattern));
+        Self(regex.expect("escaped patterns are valid"))
+    }
+
+    fn build(pattern: &str) -> Result<Regex, regex::Error> {
+        RegexBuilder::new(pattern).case_insensitive(true).build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex(pattern: &str) -> Pattern {
+        Pattern::regex(pattern).unwrap()
+    }
+
+    #[test]
+    fn matches_exact_names_and_globs() {
+        assert!(Pattern::exact("Hookshot").is_match("hookshot"));
+        assert!(!Pattern::exact("Hookshot").is_match("Progressive Hookshot"));
+        assert!(Pattern::exact("Small Key (Forest Temple)").is_match("small key (forest temple)"));
+
+        let glob = Pattern::glob("*Key");
+        assert!(!glob.is_match("Small Key (Forest Temple)"));
+        assert!(glob.is_match("Boss Key"));
+        assert!(Pattern::glob("*key*").is_match("Small Key (Forest Temple)"));
+        assert!(Pattern::glob("Progressive ?word").is_match("Progressive Sword"));
+        assert!(!Pattern::glob("Progressive ?word").is_match("Progressive Sword 2"));
+        assert!(!Pattern::glob("Key.").is_match("Keys"));
+    }
+
+    #[test]
+    fn matches_regexes() {
+        assert!(regex("key").is_match("Small Key"));
+        assert!(regex("^(Small|Boss) Key$").is_match("boss key"));
+        assert!(!regex("^(Small|Boss) Key$").is_match("Big Key"));
+        assert!(regex(r"Heart Container \d+").is_match("Heart Container 12"));
+        assert!(regex(r"^[A-C]\w{2,3}$").is_match("bomb"));
+        assert!(!regex(r"^[A-C]\w{2,3}$").is_match("bombs!"));
+    }
+
+    #[test]
+    fn matches_in_linear_time() {
+        // These take seconds with a backtracking engine.
+        let text = "a".repeat(5000);
+        assert!(!regex("^(a|a)*b$").is_match(&text));
+        assert!(!Pattern::glob("*a*a*a*a*a*b").is_match(&text));
+    }
+
+    #[test]
+    fn reports_invalid_regexes() {
+        assert!(Pattern::regex("(Key").is_err());
+        assert!(Pattern::regex("Key)").is_err());
+        assert!(Pattern::regex("*Key").is_err());
+        assert!(Pattern::regex("[z-a]").is_err());
+    }
+}
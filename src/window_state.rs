@@ -0,0 +1,94 @@
+//! Window geometry and last screen of the GUI, saved in the config on exit.
+//!
+//! iced cannot tell where the screens are before the window is open, so a
+//! saved position could be on a screen that is gone by the next start. Only
+//! the size is saved, the window opening centered on the primary screen, and
+//! positions are only kept to move between the window and the overlay.
+
+use iced::window::{self, Position};
+use iced::{Point, Size};
+use serde::{Deserialize, Serialize};
+
+use crate::overlay;
+use crate::session::Section;
+use crate::update::Route;
+
+const DEFAULT_SIZE: (u32, u32) = (1024, 768);
+/// Smallest size restored, the window itself can shrink down to
+/// [`overlay::MIN_SIZE`].
+const MIN_SIZE: (u32, u32) = (400, 300);
+
+/// Largest size restored, the one of the largest common screens.
+const MAX_SIZE: (u32, u32) = (7680, 4320);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowState {
+    pub width: u32,
+    pub height: u32,
+    /// Centered while `None`, until the window is first moved. Not saved.
+    #[serde(skip)]
+    pub position: Option<(i32, i32)>,
+    pub maximized: bool,
+    pub route: Route,
+    pub section: Section,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_SIZE.0,
+            height: DEFAULT_SIZE.1,
+            position: None,
+            maximized: false,
+            route: Route::Login,
+            section: Section::default(),
+        }
+    }
+}
+
+impl WindowState {
+    /// Size and position to open the window with.
+    pub fn settings(&self) -> window::Settings {
+        let width = self.width.clamp(MIN_SIZE.0, MAX_SIZE.0);
+        let height = self.height.clamp(MIN_SIZE.1, MAX_SIZE.1);
+
+        window::Settings {
+            size: Size::new(width as f32, height as f32),
+            min_size: Some(Size::new(overlay::MIN_SIZE.0 as f32, overlay::MIN_SIZE.1 as f32)),
+            position: position(self.position),
+            exit_on_close_request: false,
+            ..Default::default()
+        }
+    }
+}
+
+/// Position the window was last moved to since the app started, centered on
+/// the primary screen until then.
+pub fn position(position: Option<(i32, i32)>) -> Position {
+    position
+        .map(|(x, y)| Position::Specific(Point::new(x as f32, y as f32)))
+        .unwrap_or(Position::Centered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_centered_at_the_saved_size() {
+        let window = WindowState {
+            width: 800,
+            height: 600,
+            position: Some((9000, 200)),
+            ..Default::default()
+        };
+        let saved: WindowState = serde_json::from_value(serde_json::to_value(&window).unwrap()).unwrap();
+        assert_eq!(saved.position, None);
+
+        let settings = saved.settings();
+        assert_eq!(settings.size, Size::new(800.0, 600.0));
+        assert_eq!(settings.position, Position::Centered);
+        assert_eq!(window.settings().position, Position::Specific(Point::new(9000.0, 200.0)));
+    }
+}